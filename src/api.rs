@@ -1,7 +1,8 @@
 use crate::*;
-use actix_web::ResponseError;
+use actix_web::{post, ResponseError};
 use near_account_id::AccountId;
 use near_crypto::PublicKey;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
 use std::fmt;
@@ -11,6 +12,24 @@ const TARGET_API: &str = "api";
 
 pub type BlockHeight = u64;
 
+const DEFAULT_DIRECTORY_LIMIT: usize = 100;
+const MAX_DIRECTORY_LIMIT: usize = 1000;
+/// The maximum number of contracts matching a prefix that are ranked by holders.
+const MAX_DIRECTORY_PREFIX_MATCHES: usize = 10000;
+const DEFAULT_NFT_TOKENS_LIMIT: u64 = 50;
+const MAX_NFT_TOKENS_LIMIT: u64 = 100;
+const FT_TOP_LIMIT: usize = 100;
+/// The maximum number of holders ranked by exact balances when scores are tied at the limit.
+const FT_TOP_MAX_CANDIDATES: usize = 1000;
+const MAX_STORAGE_ACCOUNTS: usize = 100;
+const MAX_BALANCES_ACCOUNTS: usize = 10000;
+const MAX_BALANCES_VERIFY_ACCOUNTS: usize = 100;
+const MAX_PORTFOLIO_ACCOUNTS: usize = 50;
+const MAX_TOKEN_GATE_ACCOUNTS: usize = 1000;
+const MAX_TOKEN_GATE_CONDITIONS: usize = 20;
+const DEFAULT_DELEGATORS_LIMIT: u64 = 100;
+const MAX_DELEGATORS_LIMIT: u64 = 1000;
+
 #[derive(Debug)]
 pub enum ServiceError {
     DatabaseError(database::DatabaseError),
@@ -18,7 +37,8 @@ pub enum ServiceError {
     ArgumentError,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
+#[serde(tag = "error")]
 pub enum HealthError {
     HighSyncLatency {
         latency: f64,
@@ -31,6 +51,108 @@ pub enum HealthError {
         sync_difference: u64,
         max_sync_difference: u64,
     },
+    MissingSubsystem {
+        subsystem: String,
+    },
+    MissingSubsystemLatency {
+        subsystem: String,
+    },
+    HighSubsystemLag {
+        subsystem: String,
+        lag_blocks: u64,
+        max_lag_blocks: u64,
+    },
+    HighSubsystemLatency {
+        subsystem: String,
+        latency: f64,
+        max_latency: f64,
+    },
+}
+
+#[derive(Deserialize)]
+pub struct DirectoryQuery {
+    pub prefix: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+pub struct AccountsRequest {
+    pub account_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct BalancesQuery {
+    /// Check the balances of accounts missing from the index with the contract.
+    #[serde(default)]
+    pub verify: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TokenGateCondition {
+    /// The account holds at least `min_balance` of the fungible token.
+    Ft {
+        token_id: String,
+        min_balance: String,
+    },
+    /// The account has the NFT contract indexed.
+    Nft { contract_id: String },
+}
+
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenGateMode {
+    /// The account passes if it meets all conditions.
+    #[default]
+    All,
+    /// The account passes if it meets at least one condition.
+    Any,
+}
+
+#[derive(Deserialize)]
+pub struct TokenGateRequest {
+    pub account_ids: Vec<String>,
+    pub conditions: Vec<TokenGateCondition>,
+    #[serde(default)]
+    pub mode: TokenGateMode,
+}
+
+#[derive(Deserialize)]
+pub struct PaginationQuery {
+    pub from_index: Option<u64>,
+    pub limit: Option<u64>,
+}
+
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BalanceFormat {
+    /// Only the raw integer balances.
+    #[default]
+    Raw,
+    /// The raw integer balances together with the balances adjusted to the token decimals.
+    Decimal,
+}
+
+#[derive(Deserialize)]
+pub struct TokenOptions {
+    /// Embed the cached FT metadata (name, symbol, decimals, icon and reference) with tokens and
+    /// the NFT contract metadata (name, symbol, icon and base_uri) with NFT contracts.
+    #[serde(default)]
+    pub metadata: bool,
+    /// Embed the number of tokens the account owns on every NFT contract.
+    #[serde(default)]
+    pub owned_count: bool,
+    /// Embed the live staked and unstaked balances for every staking pool.
+    #[serde(default)]
+    pub staking_balances: bool,
+    /// Include the state and balances of the account's lockup contract.
+    #[serde(default)]
+    pub lockup: bool,
+    /// Include the native NEAR balance as a pseudo-token with the `near` contract ID.
+    #[serde(default)]
+    pub near: bool,
+    #[serde(default)]
+    pub format: BalanceFormat,
 }
 
 impl From<redis::RedisError> for ServiceError {
@@ -56,7 +178,7 @@ impl fmt::Display for ServiceError {
         match *self {
             ServiceError::DatabaseError(ref err) => write!(f, "Database Error: {:?}", err),
             ServiceError::ArgumentError => write!(f, "Invalid argument"),
-            ServiceError::RpcError(ref err) => write!(f, "Rpc Error: {:?}", err),
+            ServiceError::RpcError(ref err) => write!(f, "Rpc Error: {}", err),
         }
     }
 }
@@ -283,6 +405,7 @@ pub mod v1 {
     #[get("/account/{account_id}/staking")]
     pub async fn staking(
         request: HttpRequest,
+        options: web::Query<TokenOptions>,
         app_state: web::Data<AppState>,
     ) -> Result<impl Responder, ServiceError> {
         let account_id =
@@ -296,22 +419,29 @@ pub mod v1 {
             .get_multiplexed_async_connection()
             .await?;
 
+        let account_id = account_id.to_string();
+
         let query_result =
-            database::query_with_prefix_parse(&mut connection, "st", &account_id.to_string())
-                .await?;
+            database::query_with_prefix_parse(&mut connection, "st", &account_id).await?;
 
-        Ok(web::Json(json!({
+        let (pools, pools_total) =
+            staking_pools(&mut connection, &account_id, query_result, &options).await?;
+
+        let mut res = json!({
             "account_id": account_id,
-            "pools": query_result.into_iter().map(|(pool_id, last_update_block_height)| json!({
-                "pool_id": pool_id,
-                "last_update_block_height": last_update_block_height,
-            })).collect::<Vec<_>>()
-        })))
+            "pools": pools,
+        });
+        if let Some(pools_total) = pools_total {
+            res["pools_total"] = pools_total;
+        }
+
+        Ok(web::Json(res))
     }
 
     #[get("/account/{account_id}/ft")]
     pub async fn ft(
         request: HttpRequest,
+        options: web::Query<TokenOptions>,
         app_state: web::Data<AppState>,
     ) -> Result<impl Responder, ServiceError> {
         let account_id =
@@ -339,19 +469,35 @@ pub mod v1 {
         )
         .await?;
 
+        let mut tokens = query_result
+            .into_iter()
+            .zip(balances.into_iter())
+            .map(|((contract_id, last_update_block_height), balance)| {
+                json!({
+                    "contract_id": contract_id,
+                    "last_update_block_height": last_update_block_height,
+                    "balance": balance,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        add_token_details(&mut connection, &mut tokens, &options).await?;
+
+        if options.near {
+            let state = query_account_state(&mut connection, &account_id).await?;
+            tokens.insert(0, near_token(state.as_ref(), &options));
+        }
+
         Ok(web::Json(json!({
             "account_id": account_id,
-            "tokens": query_result.into_iter().zip(balances.into_iter()).map(|((contract_id, last_update_block_height), balance)| json!({
-                "contract_id": contract_id,
-                "last_update_block_height": last_update_block_height,
-                "balance": balance,
-            })).collect::<Vec<_>>()
+            "tokens": tokens,
         })))
     }
 
     #[get("/account/{account_id}/nft")]
     pub async fn nft(
         request: HttpRequest,
+        options: web::Query<TokenOptions>,
         app_state: web::Data<AppState>,
     ) -> Result<impl Responder, ServiceError> {
         let account_id =
@@ -365,22 +511,156 @@ pub mod v1 {
             .get_multiplexed_async_connection()
             .await?;
 
+        let account_id = account_id.to_string();
+
         let query_result =
-            database::query_with_prefix_parse(&mut connection, "nf", &account_id.to_string())
-                .await?;
+            database::query_with_prefix_parse(&mut connection, "nf", &account_id).await?;
+
+        let tokens = nft_contracts(&mut connection, &account_id, query_result, &options).await?;
+
+        Ok(web::Json(json!({
+            "account_id": account_id,
+            "tokens": tokens,
+        })))
+    }
+
+    #[get("/account/{account_id}/keys")]
+    pub async fn account_keys(
+        request: HttpRequest,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let account_id =
+            AccountId::try_from(request.match_info().get("account_id").unwrap().to_string())
+                .map_err(|_| ServiceError::ArgumentError)?;
+
+        tracing::debug!(target: TARGET_API, "Looking up public keys for account_id: {}", account_id);
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let query_result =
+            database::query_with_prefix(&mut connection, "ak", account_id.as_ref()).await?;
 
         Ok(web::Json(json!({
             "account_id": account_id,
-            "tokens": query_result.into_iter().map(|(contract_id, last_update_block_height)| json!({
-                "contract_id": contract_id,
-                "last_update_block_height": last_update_block_height,
+            "keys": query_result.into_iter().map(|(public_key, permission)| json!({
+                "public_key": public_key,
+                "permission_kind": if permission == "f" { "FullAccess" } else { "FunctionCall" },
             })).collect::<Vec<_>>()
         })))
     }
 
+    #[get("/account/{account_id}/access_keys")]
+    pub async fn account_access_keys(
+        request: HttpRequest,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let account_id =
+            AccountId::try_from(request.match_info().get("account_id").unwrap().to_string())
+                .map_err(|_| ServiceError::ArgumentError)?;
+
+        tracing::debug!(target: TARGET_API, "Looking up access keys for account_id: {}", account_id);
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let account_id = account_id.to_string();
+
+        let access_keys = rpc::get_access_keys(&account_id).await?.unwrap_or_default();
+        let indexed = database::query_hget_many(
+            &mut connection,
+            &access_keys
+                .iter()
+                .map(|key| {
+                    (
+                        format!("pk:{}", key["public_key"].as_str().unwrap_or_default()),
+                        account_id.as_str(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+        Ok(web::Json(json!({
+            "account_id": account_id,
+            "keys": access_keys.iter().zip(indexed).map(|(key, indexed)| {
+                let access_key = &key["access_key"];
+                let function_call = &access_key["permission"]["FunctionCall"];
+                json!({
+                    "public_key": key["public_key"],
+                    "nonce": access_key["nonce"],
+                    "permission_kind": if function_call.is_null() { "FullAccess" } else { "FunctionCall" },
+                    "allowance": function_call["allowance"],
+                    "receiver_id": function_call["receiver_id"],
+                    "method_names": function_call["method_names"],
+                    "indexed": indexed.is_some(),
+                })
+            }).collect::<Vec<_>>()
+        })))
+    }
+
+    #[get("/account/{account_id}/nft/{contract_id}/tokens")]
+    pub async fn nft_tokens(
+        request: HttpRequest,
+        query: web::Query<PaginationQuery>,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let account_id =
+            AccountId::try_from(request.match_info().get("account_id").unwrap().to_string())
+                .map_err(|_| ServiceError::ArgumentError)?;
+        let contract_id =
+            AccountId::try_from(request.match_info().get("contract_id").unwrap().to_string())
+                .map_err(|_| ServiceError::ArgumentError)?;
+        let from_index = query.from_index.unwrap_or(0);
+        let limit = query.limit.unwrap_or(DEFAULT_NFT_TOKENS_LIMIT);
+        if limit == 0 || limit > MAX_NFT_TOKENS_LIMIT {
+            return Err(ServiceError::ArgumentError);
+        }
+
+        tracing::debug!(target: TARGET_API, "Looking up NFT tokens for account_id: {} on {}", account_id, contract_id);
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let account_id = account_id.to_string();
+        let contract_id = contract_id.to_string();
+
+        let last_update_block_height: Option<BlockHeight> =
+            database::query_hget(&mut connection, &format!("nf:{}", account_id), &contract_id)
+                .await?
+                .and_then(|v| v.parse().ok());
+
+        let res = crate::nft::get_tokens_for_owner(
+            &mut connection,
+            &contract_id,
+            &account_id,
+            last_update_block_height,
+            from_index,
+            limit,
+        )
+        .await?;
+
+        Ok(web::Json(json!({
+            "account_id": account_id,
+            "contract_id": contract_id,
+            "last_update_block_height": last_update_block_height,
+            "from_index": from_index,
+            "limit": limit,
+            "total": res.total,
+            "tokens": res.tokens,
+        })))
+    }
+
     #[get("/ft/{token_id}/top")]
     pub async fn ft_top(
         request: HttpRequest,
+        options: web::Query<TokenOptions>,
         app_state: web::Data<AppState>,
     ) -> Result<impl Responder, ServiceError> {
         let token_id =
@@ -396,95 +676,487 @@ pub mod v1 {
 
         let token_id = token_id.to_string();
 
-        let query_result =
-            database::query_zset_by_score(&mut connection, &format!("tb:{}", token_id), 100)
-                .await?;
-        let balances = database::query_balances(
+        let top_holders = database::query_top_balances(
             &mut connection,
-            query_result
-                .iter()
-                .map(|account_id| (token_id.as_str(), account_id.as_str()))
-                .collect::<Vec<_>>()
-                .as_slice(),
+            &token_id,
+            FT_TOP_LIMIT,
+            FT_TOP_MAX_CANDIDATES,
         )
         .await?;
 
-        let mut top_holders = query_result
-            .into_iter()
-            .zip(balances.into_iter())
-            .collect::<Vec<_>>();
-
-        top_holders.sort_unstable_by(|a, b| {
-            (
-                b.1.as_ref()
-                    .and_then(|b| b.parse::<u128>().ok())
-                    .unwrap_or(0),
-                &b.0,
-            )
-                .cmp(&(
-                    a.1.as_ref()
-                        .and_then(|b| b.parse::<u128>().ok())
-                        .unwrap_or(0),
-                    &a.0,
-                ))
-        });
-
-        Ok(web::Json(json!({
+        let mut res = json!({
             "token_id": token_id,
             "accounts": top_holders.iter().map(|(account_id, balance)| json!({
                 "account_id": account_id,
                 "balance": balance,
             })).collect::<Vec<_>>()
-        })))
+        });
+
+        if options.metadata || options.format == BalanceFormat::Decimal {
+            let ft_metadata = metadata::get_ft_metadata(&mut connection, &[token_id.as_str()])
+                .await?
+                .remove(&token_id)
+                .flatten();
+            if options.format == BalanceFormat::Decimal {
+                for account in res["accounts"].as_array_mut().unwrap() {
+                    account["balance_decimal"] =
+                        json!(decimal_balance(&account["balance"], ft_metadata.as_ref()));
+                }
+            }
+            if options.metadata {
+                res["metadata"] = json!(ft_metadata);
+            }
+        }
+
+        Ok(web::Json(res))
     }
 
-    #[get("/account/{account_id}/full")]
-    pub async fn account_full(
+    #[get("/ft/{token_id}/storage/{account_id}")]
+    pub async fn ft_storage(
         request: HttpRequest,
         app_state: web::Data<AppState>,
     ) -> Result<impl Responder, ServiceError> {
+        let token_id =
+            AccountId::try_from(request.match_info().get("token_id").unwrap().to_string())
+                .map_err(|_| ServiceError::ArgumentError)?;
         let account_id =
             AccountId::try_from(request.match_info().get("account_id").unwrap().to_string())
                 .map_err(|_| ServiceError::ArgumentError)?;
 
-        tracing::debug!(target: TARGET_API, "Looking full data for account_id: {}", account_id);
+        tracing::debug!(target: TARGET_API, "Checking storage registration for account_id: {} on {}", account_id, token_id);
 
         let mut connection = app_state
             .redis_client
             .get_multiplexed_async_connection()
             .await?;
 
-        let account_id = account_id.to_string();
+        let token_id = token_id.to_string();
 
-        let query_result =
-            database::query_with_prefix_parse(&mut connection, "st", &account_id.to_string())
-                .await?;
+        let storage_balance_bounds =
+            metadata::get_storage_balance_bounds(&mut connection, &token_id).await?;
+        let mut accounts =
+            storage_registrations(&mut connection, &token_id, vec![account_id.to_string()]).await?;
+
+        let mut res = accounts.pop().unwrap();
+        res["token_id"] = json!(token_id);
+        res["storage_balance_bounds"] = json!(storage_balance_bounds);
+
+        Ok(web::Json(res))
+    }
 
-        let pools = query_result
+    #[post("/ft/{token_id}/storage")]
+    pub async fn ft_storage_batch(
+        request: HttpRequest,
+        body: web::Json<AccountsRequest>,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let token_id =
+            AccountId::try_from(request.match_info().get("token_id").unwrap().to_string())
+                .map_err(|_| ServiceError::ArgumentError)?;
+        let account_ids = body
+            .into_inner()
+            .account_ids
             .into_iter()
-            .map(|(pool_id, last_update_block_height)| {
+            .map(|account_id| AccountId::try_from(account_id).map(|a| a.to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ServiceError::ArgumentError)?;
+        if account_ids.len() > MAX_STORAGE_ACCOUNTS {
+            return Err(ServiceError::ArgumentError);
+        }
+
+        tracing::debug!(target: TARGET_API, "Checking storage registration for {} accounts on {}", account_ids.len(), token_id);
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let token_id = token_id.to_string();
+
+        let storage_balance_bounds =
+            metadata::get_storage_balance_bounds(&mut connection, &token_id).await?;
+        let accounts = storage_registrations(&mut connection, &token_id, account_ids).await?;
+
+        Ok(web::Json(json!({
+            "token_id": token_id,
+            "storage_balance_bounds": storage_balance_bounds,
+            "accounts": accounts,
+        })))
+    }
+
+    #[post("/ft/{token_id}/balances")]
+    pub async fn ft_balances(
+        request: HttpRequest,
+        body: web::Json<AccountsRequest>,
+        query: web::Query<BalancesQuery>,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let token_id =
+            AccountId::try_from(request.match_info().get("token_id").unwrap().to_string())
+                .map_err(|_| ServiceError::ArgumentError)?;
+        let account_ids = body
+            .into_inner()
+            .account_ids
+            .into_iter()
+            .map(|account_id| AccountId::try_from(account_id).map(|a| a.to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ServiceError::ArgumentError)?;
+        if account_ids.len() > MAX_BALANCES_ACCOUNTS {
+            return Err(ServiceError::ArgumentError);
+        }
+
+        tracing::debug!(target: TARGET_API, "Looking up balances of {} accounts on {}", account_ids.len(), token_id);
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let token_id = token_id.to_string();
+
+        let pairs = account_ids
+            .iter()
+            .map(|account_id| (token_id.as_str(), account_id.as_str()))
+            .collect::<Vec<_>>();
+        let balances = database::query_balances(&mut connection, &pairs).await?;
+        let mut accounts = account_ids
+            .iter()
+            .zip(balances)
+            .map(|(account_id, balance)| {
                 json!({
-                    "pool_id": pool_id,
-                    "last_update_block_height": last_update_block_height,
+                    "account_id": account_id,
+                    "source": balance.as_ref().map(|_| "index"),
+                    "balance": balance,
                 })
             })
             .collect::<Vec<_>>();
 
-        let query_result =
-            database::query_with_prefix_parse(&mut connection, "ft", &account_id).await?;
+        if query.verify {
+            let missing = accounts
+                .iter()
+                .enumerate()
+                .filter(|(_, account)| account["balance"].is_null())
+                .map(|(i, _)| i)
+                .take(MAX_BALANCES_VERIFY_ACCOUNTS)
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                let missing_account_ids = missing
+                    .iter()
+                    .map(|&i| account_ids[i].clone())
+                    .collect::<Vec<_>>();
+                let balances =
+                    rpc::get_ft_balances_of_accounts(&token_id, &missing_account_ids).await?;
+                for (i, balance) in missing.into_iter().zip(balances) {
+                    if let Some(balance) = balance {
+                        accounts[i]["balance"] = json!(balance);
+                        accounts[i]["source"] = json!("rpc");
+                    }
+                }
+            }
+        }
+
+        Ok(web::Json(json!({
+            "token_id": token_id,
+            "accounts": accounts,
+        })))
+    }
+
+    #[post("/token_gate")]
+    pub async fn token_gate(
+        body: web::Json<TokenGateRequest>,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let body = body.into_inner();
+        let account_ids = body
+            .account_ids
+            .into_iter()
+            .map(|account_id| AccountId::try_from(account_id).map(|a| a.to_string()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| ServiceError::ArgumentError)?;
+        if account_ids.len() > MAX_TOKEN_GATE_ACCOUNTS
+            || body.conditions.is_empty()
+            || body.conditions.len() > MAX_TOKEN_GATE_CONDITIONS
+        {
+            return Err(ServiceError::ArgumentError);
+        }
+
+        let mut token_ids = vec![];
+        let mut contract_ids = vec![];
+        for condition in &body.conditions {
+            match condition {
+                TokenGateCondition::Ft {
+                    token_id,
+                    min_balance,
+                } => {
+                    AccountId::from_str(token_id).map_err(|_| ServiceError::ArgumentError)?;
+                    min_balance
+                        .parse::<u128>()
+                        .map_err(|_| ServiceError::ArgumentError)?;
+                    token_ids.push(token_id.as_str());
+                }
+                TokenGateCondition::Nft { contract_id } => {
+                    AccountId::from_str(contract_id).map_err(|_| ServiceError::ArgumentError)?;
+                    contract_ids.push(contract_id.as_str());
+                }
+            }
+        }
+
+        tracing::debug!(target: TARGET_API, "Checking token gate for {} accounts with {} conditions", account_ids.len(), body.conditions.len());
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let (balances, owned) =
+            database::query_holdings(&mut connection, &account_ids, &token_ids, &contract_ids)
+                .await?;
+
+        let accounts = account_ids
+            .iter()
+            .enumerate()
+            .map(|(i, account_id)| {
+                let (mut token_index, mut contract_index) = (0, 0);
+                let evidence = body
+                    .conditions
+                    .iter()
+                    .map(|condition| match condition {
+                        TokenGateCondition::Ft {
+                            token_id,
+                            min_balance,
+                        } => {
+                            let balance = &balances[token_index][i];
+                            token_index += 1;
+                            let pass = balance
+                                .as_ref()
+                                .and_then(|b| b.parse::<u128>().ok())
+                                .unwrap_or(0)
+                                >= min_balance.parse::<u128>().unwrap();
+                            json!({
+                                "type": "ft",
+                                "token_id": token_id,
+                                "min_balance": min_balance,
+                                "balance": balance,
+                                "pass": pass,
+                            })
+                        }
+                        TokenGateCondition::Nft { contract_id } => {
+                            let pass = owned[i][contract_index];
+                            contract_index += 1;
+                            json!({
+                                "type": "nft",
+                                "contract_id": contract_id,
+                                "pass": pass,
+                            })
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let mut passes = evidence.iter().map(|e| e["pass"].as_bool().unwrap());
+                let pass = match body.mode {
+                    TokenGateMode::All => passes.all(|pass| pass),
+                    TokenGateMode::Any => passes.any(|pass| pass),
+                };
+                json!({
+                    "account_id": account_id,
+                    "pass": pass,
+                    "conditions": evidence,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(web::Json(json!({
+            "accounts": accounts,
+        })))
+    }
+
+    /// Returns the storage registration of every account on the token. Accounts with a positive
+    /// indexed balance are registered, so RPC is only called for the rest of the accounts.
+    async fn storage_registrations(
+        connection: &mut redis::aio::MultiplexedConnection,
+        token_id: &str,
+        account_ids: Vec<String>,
+    ) -> Result<Vec<serde_json::Value>, ServiceError> {
         let balances = database::query_balances(
-            &mut connection,
-            query_result
+            connection,
+            account_ids
                 .iter()
-                .map(|(token_id, _)| (token_id.as_str(), account_id.as_str()))
+                .map(|account_id| (token_id, account_id.as_str()))
                 .collect::<Vec<_>>()
                 .as_slice(),
         )
         .await?;
-        let tokens = query_result
+        let is_indexed = balances
+            .iter()
+            .map(|balance| {
+                balance
+                    .as_ref()
+                    .and_then(|b| b.parse::<u128>().ok())
+                    .unwrap_or(0)
+                    > 0
+            })
+            .collect::<Vec<_>>();
+        let missing = account_ids
+            .iter()
+            .zip(is_indexed.iter())
+            .filter(|(_, is_indexed)| !**is_indexed)
+            .map(|(account_id, _)| account_id.clone())
+            .collect::<Vec<_>>();
+        let mut storage_balances = rpc::get_storage_balances(token_id, &missing)
+            .await?
+            .into_iter();
+
+        Ok(account_ids
             .into_iter()
-            .zip(balances.into_iter())
-            .map(|((contract_id, last_update_block_height), balance)| {
+            .zip(is_indexed)
+            .map(|(account_id, is_indexed)| {
+                if is_indexed {
+                    json!({
+                        "account_id": account_id,
+                        "registered": true,
+                        "storage_balance": null,
+                        "source": "index",
+                    })
+                } else {
+                    let storage_balance = storage_balances.next().flatten();
+                    json!({
+                        "account_id": account_id,
+                        "registered": storage_balance.as_ref().map(|b| b.is_some()),
+                        "storage_balance": storage_balance.flatten(),
+                        "source": "rpc",
+                    })
+                }
+            })
+            .collect())
+    }
+
+    #[get("/account/{account_id}/full")]
+    pub async fn account_full(
+        request: HttpRequest,
+        options: web::Query<TokenOptions>,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let account_id =
+            AccountId::try_from(request.match_info().get("account_id").unwrap().to_string())
+                .map_err(|_| ServiceError::ArgumentError)?;
+
+        tracing::debug!(target: TARGET_API, "Looking full data for account_id: {}", account_id);
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let account_id = account_id.to_string();
+
+        let res =
+            account_full_data(&mut connection, &account_id, &options, &app_state.config).await?;
+
+        Ok(web::Json(res))
+    }
+
+    #[get("/public_key/{public_key}/portfolio")]
+    pub async fn public_key_portfolio(
+        request: HttpRequest,
+        options: web::Query<TokenOptions>,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let public_key = PublicKey::from_str(request.match_info().get("public_key").unwrap())
+            .map_err(|_| ServiceError::ArgumentError)?;
+
+        tracing::debug!(target: TARGET_API, "Looking up portfolio for public_key: {}", public_key);
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let public_key = public_key.to_string();
+
+        let mut account_ids = database::query_with_prefix(&mut connection, "pk", &public_key)
+            .await?
+            .into_iter()
+            .map(|(account_id, _)| account_id)
+            .collect::<Vec<_>>();
+        account_ids.sort_unstable();
+        account_ids.truncate(MAX_PORTFOLIO_ACCOUNTS);
+
+        let accounts = futures_util::future::try_join_all(account_ids.iter().map(|account_id| {
+            let mut connection = connection.clone();
+            let options = &options;
+            let config = &app_state.config;
+            async move { account_full_data(&mut connection, account_id, options, config).await }
+        }))
+        .await?;
+
+        let mut token_totals: Vec<(String, u128, u64)> = vec![];
+        let (mut liquid_balance, mut staked_balance) = (0u128, 0u128);
+        for account in &accounts {
+            for token in account["tokens"].as_array().into_iter().flatten() {
+                let (Some(contract_id), Some(balance)) = (
+                    token["contract_id"].as_str(),
+                    token["balance"]
+                        .as_str()
+                        .and_then(|b| b.parse::<u128>().ok()),
+                ) else {
+                    continue;
+                };
+                match token_totals.iter_mut().find(|(c, _, _)| c == contract_id) {
+                    Some((_, total, holders)) => {
+                        *total = total.saturating_add(balance);
+                        *holders += 1;
+                    }
+                    None => token_totals.push((contract_id.to_string(), balance, 1)),
+                }
+            }
+            let totals = &account["totals"];
+            liquid_balance = liquid_balance.saturating_add(
+                totals["liquid_balance"]
+                    .as_str()
+                    .and_then(|b| b.parse::<u128>().ok())
+                    .unwrap_or(0),
+            );
+            staked_balance = staked_balance.saturating_add(
+                totals["staked_balance"]
+                    .as_str()
+                    .and_then(|b| b.parse::<u128>().ok())
+                    .unwrap_or(0),
+            );
+        }
+        token_totals.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(web::Json(json!({
+            "public_key": public_key,
+            "account_ids": account_ids,
+            "accounts": accounts,
+            "totals": {
+                "liquid_balance": liquid_balance.to_string(),
+                "staked_balance": staked_balance.to_string(),
+                "tokens": token_totals.into_iter().map(|(contract_id, balance, accounts)| json!({
+                    "contract_id": contract_id,
+                    "balance": balance.to_string(),
+                    "accounts": accounts,
+                })).collect::<Vec<_>>(),
+            },
+        })))
+    }
+
+    /// Returns the full data of the account: staking pools, tokens, NFT contracts, account state
+    /// and the totals, as served by `account_full`.
+    async fn account_full_data(
+        connection: &mut redis::aio::MultiplexedConnection,
+        account_id: &str,
+        options: &TokenOptions,
+        config: &Config,
+    ) -> Result<serde_json::Value, ServiceError> {
+        let snapshot = database::query_account_snapshot(connection, account_id).await?;
+
+        let (pools, pools_total) =
+            staking_pools(connection, account_id, snapshot.pools, options).await?;
+
+        let mut tokens = snapshot
+            .tokens
+            .into_iter()
+            .map(|(contract_id, last_update_block_height, balance)| {
                 json!({
                     "contract_id": contract_id,
                     "last_update_block_height": last_update_block_height,
@@ -493,21 +1165,124 @@ pub mod v1 {
             })
             .collect::<Vec<_>>();
 
+        add_token_details(connection, &mut tokens, options).await?;
+
+        let nfts = nft_contracts(connection, account_id, snapshot.nfts, options).await?;
+
+        let state = snapshot
+            .state
+            .filter(|state| !state.is_empty())
+            .and_then(|state| serde_json::from_str::<serde_json::Value>(&state).ok());
+
+        if options.near {
+            tokens.insert(0, near_token(state.as_ref(), options));
+        }
+
+        let mut res = json!({
+            "account_id": account_id,
+            "block_height": snapshot.latest_block,
+            "pools": pools,
+            "tokens": tokens,
+            "nfts": nfts,
+            "state": state.as_ref().map(|state| account_state(state, options, config)),
+        });
+        if let Some((balance, locked, storage_bytes)) = state.as_ref().and_then(parse_account_state)
+        {
+            let storage_cost = balance::storage_cost(storage_bytes, config.storage_price_per_byte);
+            let delegated_staked_balance = pools_total
+                .as_ref()
+                .and_then(|total| total["staked_balance"].as_str()?.parse::<u128>().ok())
+                .unwrap_or(0);
+            res["totals"] = json!({
+                "liquid_balance": balance::available_balance(balance, locked, storage_cost).to_string(),
+                "staked_balance": locked.saturating_add(delegated_staked_balance).to_string(),
+            });
+        }
+        if let Some(pools_total) = pools_total {
+            res["pools_total"] = pools_total;
+        }
+        if options.lockup {
+            res["lockup"] = json!(lockup(connection, account_id, options, config).await?);
+        }
+
+        Ok(res)
+    }
+
+    #[get("/near/top")]
+    pub async fn near_top(app_state: web::Data<AppState>) -> Result<impl Responder, ServiceError> {
+        tracing::debug!(target: TARGET_API, "Retrieving top accounts by native balance");
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
         let query_result =
-            database::query_with_prefix_parse(&mut connection, "nf", &account_id.to_string())
+            database::query_zset_by_score(&mut connection, crate::near_top::NEAR_TOP_KEY, 100)
                 .await?;
+        let states = database::query_hget_many(
+            &mut connection,
+            &query_result
+                .iter()
+                .map(|account_id| ("accounts".to_string(), account_id.as_str()))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
 
-        let nfts = query_result
+        let mut top_accounts = query_result
             .into_iter()
-            .map(|(contract_id, last_update_block_height)| {
-                json!({
-                    "contract_id": contract_id,
-                    "last_update_block_height": last_update_block_height,
-                })
+            .zip(states.into_iter())
+            .map(|(account_id, state)| {
+                let balance = state
+                    .and_then(|state| serde_json::from_str::<serde_json::Value>(&state).ok())
+                    .and_then(|state| state["b"].as_str()?.parse::<u128>().ok());
+                (account_id, balance)
             })
             .collect::<Vec<_>>();
 
-        let state = database::query_hget(&mut connection, "accounts", &account_id)
+        top_accounts.sort_unstable_by(|a, b| (b.1, &a.0).cmp(&(a.1, &b.0)));
+
+        Ok(web::Json(json!({
+            "accounts": top_accounts.iter().map(|(account_id, balance)| json!({
+                "account_id": account_id,
+                "balance": balance.map(|b| b.to_string()),
+            })).collect::<Vec<_>>()
+        })))
+    }
+
+    /// Returns the native NEAR balance as a token entry.
+    fn near_token(state: Option<&serde_json::Value>, options: &TokenOptions) -> serde_json::Value {
+        let balance = state.map(|state| &state["b"]);
+        let mut token = json!({
+            "contract_id": "near",
+            // The account state in the `accounts` hash only has the `b`, `l` and `s` fields, so the
+            // block height of the last change is unknown.
+            "last_update_block_height": null,
+            "balance": balance,
+        });
+        if options.format == BalanceFormat::Decimal {
+            token["balance_decimal"] = json!(balance
+                .and_then(|b| b.as_str())
+                .and_then(|b| balance::format_decimal(b, balance::NEAR_DECIMALS)));
+        }
+        if options.metadata {
+            token["metadata"] = json!(rpc::FtMetadata {
+                name: "NEAR".to_string(),
+                symbol: "NEAR".to_string(),
+                decimals: balance::NEAR_DECIMALS,
+                icon: None,
+                reference: None,
+            });
+        }
+        token
+    }
+
+    /// Returns the parsed account state from the `accounts` hash.
+    async fn query_account_state(
+        connection: &mut redis::aio::MultiplexedConnection,
+        account_id: &str,
+    ) -> Result<Option<serde_json::Value>, ServiceError> {
+        Ok(database::query_hget(connection, "accounts", account_id)
             .await?
             .and_then(|state| {
                 if state.is_empty() {
@@ -515,18 +1290,368 @@ pub mod v1 {
                 } else {
                     serde_json::from_str::<serde_json::Value>(&state).ok()
                 }
-            });
+            }))
+    }
+
+    /// Returns the balance, locked balance and storage bytes from the account state.
+    fn parse_account_state(state: &serde_json::Value) -> Option<(u128, u128, u64)> {
+        Some((
+            state["b"].as_str()?.parse().ok()?,
+            state["l"].as_str()?.parse().ok()?,
+            state["s"].as_u64()?,
+        ))
+    }
+
+    fn account_state(
+        state: &serde_json::Value,
+        options: &TokenOptions,
+        config: &Config,
+    ) -> serde_json::Value {
+        let mut res = json!({
+            "balance": state["b"],
+            "locked": state["l"],
+            "storage_bytes": state["s"],
+        });
+        if let Some((balance, locked, storage_bytes)) = parse_account_state(state) {
+            let storage_cost = balance::storage_cost(storage_bytes, config.storage_price_per_byte);
+            res["storage_cost"] = json!(storage_cost.to_string());
+            res["available_balance"] =
+                json!(balance::available_balance(balance, locked, storage_cost).to_string());
+        }
+        if options.format == BalanceFormat::Decimal {
+            for field in ["balance", "locked", "storage_cost", "available_balance"] {
+                res[format!("{}_decimal", field)] = json!(res[field]
+                    .as_str()
+                    .and_then(|b| balance::format_decimal(b, balance::NEAR_DECIMALS)));
+            }
+        }
+        res
+    }
+
+    /// Returns the lockup section for the owner, or `None` if the lockup account doesn't exist.
+    async fn lockup(
+        connection: &mut redis::aio::MultiplexedConnection,
+        owner_id: &str,
+        options: &TokenOptions,
+        config: &Config,
+    ) -> Result<Option<serde_json::Value>, ServiceError> {
+        let lockup_id = lockup::lockup_account_id(owner_id);
+        let Some(state) = query_account_state(connection, &lockup_id).await? else {
+            return Ok(None);
+        };
+        let balances = rpc::get_lockup_balances(&lockup_id).await?;
+        Ok(Some(json!({
+            "account_id": lockup_id,
+            "state": account_state(&state, options, config),
+            "owners_balance": balances.as_ref().map(|b| &b.owners_balance),
+            "liquid_owners_balance": balances.as_ref().map(|b| &b.liquid_owners_balance),
+            "locked_amount": balances.as_ref().map(|b| &b.locked_amount),
+        })))
+    }
+
+    /// Adds the optional `metadata` and `balance_decimal` fields to every token entry based on
+    /// its `contract_id`.
+    async fn add_token_details(
+        connection: &mut redis::aio::MultiplexedConnection,
+        tokens: &mut [serde_json::Value],
+        options: &TokenOptions,
+    ) -> Result<(), ServiceError> {
+        if !options.metadata && options.format != BalanceFormat::Decimal {
+            return Ok(());
+        }
+        let token_ids = tokens
+            .iter()
+            .filter_map(|token| token["contract_id"].as_str().map(|s| s.to_string()))
+            .collect::<Vec<_>>();
+        let mut metadata = metadata::get_ft_metadata(
+            connection,
+            &token_ids.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+        )
+        .await?;
+        for token in tokens.iter_mut() {
+            let m = token["contract_id"]
+                .as_str()
+                .and_then(|contract_id| metadata.remove(contract_id))
+                .flatten();
+            if options.format == BalanceFormat::Decimal {
+                token["balance_decimal"] = json!(decimal_balance(&token["balance"], m.as_ref()));
+            }
+            if options.metadata {
+                token["metadata"] = json!(m);
+            }
+        }
+        Ok(())
+    }
+
+    #[get("/staking/{pool_id}")]
+    pub async fn staking_pool(
+        request: HttpRequest,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let pool_id = AccountId::try_from(request.match_info().get("pool_id").unwrap().to_string())
+            .map_err(|_| ServiceError::ArgumentError)?;
+
+        tracing::debug!(target: TARGET_API, "Looking up staking pool: {}", pool_id);
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let pool_id = pool_id.to_string();
+
+        let (info, is_validator) =
+            crate::staking::get_staking_pool_info(&mut connection, &pool_id).await?;
 
         Ok(web::Json(json!({
-            "account_id": account_id,
-            "pools": pools,
-            "tokens": tokens,
-            "nfts": nfts,
-            "state": state.map(|state| json!({
-                "balance": state["b"],
-                "locked": state["l"],
-                "storage_bytes": state["s"],
+            "pool_id": pool_id,
+            "owner_id": info.as_ref().map(|info| &info.owner_id),
+            "reward_fee_fraction": info.as_ref().map(|info| &info.reward_fee_fraction),
+            "total_staked_balance": info.as_ref().map(|info| &info.total_staked_balance),
+            "number_of_delegators": info.as_ref().map(|info| info.number_of_accounts),
+            "is_validator": is_validator,
+        })))
+    }
+
+    #[get("/staking/{pool_id}/delegators")]
+    pub async fn staking_pool_delegators(
+        request: HttpRequest,
+        query: web::Query<PaginationQuery>,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        let pool_id = AccountId::try_from(request.match_info().get("pool_id").unwrap().to_string())
+            .map_err(|_| ServiceError::ArgumentError)?;
+        let from_index = query.from_index.unwrap_or(0);
+        let limit = query.limit.unwrap_or(DEFAULT_DELEGATORS_LIMIT);
+        if limit == 0 || limit > MAX_DELEGATORS_LIMIT {
+            return Err(ServiceError::ArgumentError);
+        }
+
+        tracing::debug!(target: TARGET_API, "Looking up delegators for pool_id: {}", pool_id);
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let pool_id = pool_id.to_string();
+
+        let query_result =
+            database::query_with_prefix_parse(&mut connection, "sd", &pool_id).await?;
+        let staked_balances: HashMap<String, u128> =
+            database::query_with_prefix(&mut connection, "sdb", &pool_id)
+                .await?
+                .into_iter()
+                .filter_map(|(account_id, balance)| Some((account_id, balance.parse().ok()?)))
+                .collect();
+
+        let mut delegators = query_result
+            .into_iter()
+            .map(|(account_id, last_update_block_height)| {
+                let staked_balance = staked_balances.get(&account_id).cloned();
+                (account_id, last_update_block_height, staked_balance)
+            })
+            .collect::<Vec<_>>();
+
+        // Delegators with known staked balances go first ordered by decreasing stake.
+        delegators.sort_unstable_by(|a, b| (b.2, &a.0).cmp(&(a.2, &b.0)));
+
+        Ok(web::Json(json!({
+            "pool_id": pool_id,
+            "total": delegators.len(),
+            "from_index": from_index,
+            "limit": limit,
+            "delegators": delegators
+                .into_iter()
+                .skip(from_index as usize)
+                .take(limit as usize)
+                .map(|(account_id, last_update_block_height, staked_balance)| json!({
+                    "account_id": account_id,
+                    "last_update_block_height": last_update_block_height,
+                    "staked_balance": staked_balance.map(|b| b.to_string()),
+                }))
+                .collect::<Vec<_>>(),
+        })))
+    }
+
+    /// Returns the staking pool entries with the optional live balances, and the total staked and
+    /// unstaked balances across all pools if the balances were requested.
+    async fn staking_pools(
+        connection: &mut redis::aio::MultiplexedConnection,
+        account_id: &str,
+        pools: Vec<(String, Option<BlockHeight>)>,
+        options: &TokenOptions,
+    ) -> Result<(Vec<serde_json::Value>, Option<serde_json::Value>), ServiceError> {
+        let mut entries = pools
+            .iter()
+            .map(|(pool_id, last_update_block_height)| {
+                json!({
+                    "pool_id": pool_id,
+                    "last_update_block_height": last_update_block_height,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if !options.staking_balances {
+            return Ok((entries, None));
+        }
+
+        let balances = crate::staking::get_staking_balances(connection, account_id, &pools).await?;
+        let mut total_staked: u128 = 0;
+        let mut total_unstaked: u128 = 0;
+        for (entry, balance) in entries.iter_mut().zip(balances) {
+            if let Some(balance) = &balance {
+                total_staked = total_staked
+                    .saturating_add(balance.staked_balance.parse::<u128>().unwrap_or(0));
+                total_unstaked = total_unstaked
+                    .saturating_add(balance.unstaked_balance.parse::<u128>().unwrap_or(0));
+            }
+            entry["staked_balance"] = json!(balance.as_ref().map(|b| &b.staked_balance));
+            entry["unstaked_balance"] = json!(balance.as_ref().map(|b| &b.unstaked_balance));
+            entry["can_withdraw"] = json!(balance.as_ref().map(|b| b.can_withdraw));
+        }
+
+        Ok((
+            entries,
+            Some(json!({
+                "staked_balance": total_staked.to_string(),
+                "unstaked_balance": total_unstaked.to_string(),
             })),
+        ))
+    }
+
+    /// Returns the NFT contract entries with the optional `metadata` and `owned_count` fields.
+    async fn nft_contracts(
+        connection: &mut redis::aio::MultiplexedConnection,
+        account_id: &str,
+        contracts: Vec<(String, Option<BlockHeight>)>,
+        options: &TokenOptions,
+    ) -> Result<Vec<serde_json::Value>, ServiceError> {
+        let mut nfts = contracts
+            .iter()
+            .map(|(contract_id, last_update_block_height)| {
+                json!({
+                    "contract_id": contract_id,
+                    "last_update_block_height": last_update_block_height,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if options.metadata {
+            let mut metadata = metadata::get_nft_metadata(
+                connection,
+                &contracts
+                    .iter()
+                    .map(|(contract_id, _)| contract_id.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+            for (entry, (contract_id, _)) in nfts.iter_mut().zip(contracts.iter()) {
+                entry["metadata"] = json!(metadata.remove(contract_id).flatten());
+            }
+        }
+
+        if options.owned_count {
+            let owned_counts =
+                crate::nft::get_supply_for_owner(connection, account_id, &contracts).await?;
+            for (entry, owned_count) in nfts.iter_mut().zip(owned_counts) {
+                entry["owned_count"] = json!(owned_count);
+            }
+        }
+
+        Ok(nfts)
+    }
+
+    /// Returns the balance adjusted to the token decimals, or `None` if either the balance or the
+    /// metadata is not available.
+    fn decimal_balance(
+        balance: &serde_json::Value,
+        metadata: Option<&rpc::FtMetadata>,
+    ) -> Option<String> {
+        balance::format_decimal(balance.as_str()?, metadata?.decimals)
+    }
+
+    #[get("/ft")]
+    pub async fn ft_directory(
+        query: web::Query<DirectoryQuery>,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        tracing::debug!(target: TARGET_API, "Listing fungible token contracts with prefix: {:?}", query.prefix);
+
+        let contracts = query_directory(&app_state, "ft", &query.into_inner()).await?;
+
+        Ok(web::Json(json!({
+            "contracts": contracts,
+        })))
+    }
+
+    #[get("/nft")]
+    pub async fn nft_directory(
+        query: web::Query<DirectoryQuery>,
+        app_state: web::Data<AppState>,
+    ) -> Result<impl Responder, ServiceError> {
+        tracing::debug!(target: TARGET_API, "Listing non-fungible token contracts with prefix: {:?}", query.prefix);
+
+        let contracts = query_directory(&app_state, "nf", &query.into_inner()).await?;
+
+        Ok(web::Json(json!({
+            "contracts": contracts,
         })))
     }
+
+    async fn query_directory(
+        app_state: &web::Data<AppState>,
+        prefix_key: &str,
+        query: &DirectoryQuery,
+    ) -> Result<Vec<serde_json::Value>, ServiceError> {
+        let limit = query.limit.unwrap_or(DEFAULT_DIRECTORY_LIMIT);
+        if limit == 0 || limit > MAX_DIRECTORY_LIMIT {
+            return Err(ServiceError::ArgumentError);
+        }
+        let prefix = query.prefix.as_deref().unwrap_or_default();
+
+        let mut connection = app_state
+            .redis_client
+            .get_multiplexed_async_connection()
+            .await?;
+
+        let key = format!("{}:{}", directory::DIRECTORY_PREFIX, prefix_key);
+        let contract_ids = if prefix.is_empty() {
+            database::query_zrange_by_rank(&mut connection, &format!("{}:holders", key), limit)
+                .await?
+        } else {
+            database::query_zrange_by_lex_prefix(
+                &mut connection,
+                &format!("{}:lex", key),
+                prefix,
+                MAX_DIRECTORY_PREFIX_MATCHES,
+            )
+            .await?
+        };
+        let entries = database::query_hmget(&mut connection, &key, &contract_ids).await?;
+
+        let mut contracts = contract_ids
+            .into_iter()
+            .zip(entries)
+            .filter_map(|(contract_id, entry)| {
+                serde_json::from_str::<directory::DirectoryEntry>(&entry?)
+                    .ok()
+                    .map(|entry| (contract_id, entry))
+            })
+            .collect::<Vec<_>>();
+
+        contracts.sort_unstable_by(|a, b| (b.1.holders, &a.0).cmp(&(a.1.holders, &b.0)));
+
+        Ok(contracts
+            .into_iter()
+            .take(limit)
+            .map(|(contract_id, entry)| {
+                json!({
+                    "contract_id": contract_id,
+                    "holders": entry.holders,
+                    "last_update_block_height": entry.last_update_block_height,
+                })
+            })
+            .collect())
+    }
 }
@@ -0,0 +1,74 @@
+/// The number of decimals in the native NEAR balance (yoctoNEAR).
+pub const NEAR_DECIMALS: u8 = 24;
+
+/// Converts a raw integer balance into an exact decimal string adjusted by the given number of
+/// decimals, e.g. `"1230000"` with `6` decimals becomes `"1.23"`.
+/// Returns `None` if the raw balance is not a valid u128 integer.
+pub fn format_decimal(raw: &str, decimals: u8) -> Option<String> {
+    let digits = raw.parse::<u128>().ok()?.to_string();
+    let decimals = decimals as usize;
+    let (integer, fraction) = if digits.len() > decimals {
+        let (integer, fraction) = digits.split_at(digits.len() - decimals);
+        (integer.to_string(), fraction.to_string())
+    } else {
+        (
+            "0".to_string(),
+            format!("{:0>width$}", digits, width = decimals),
+        )
+    };
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        Some(integer)
+    } else {
+        Some(format!("{}.{}", integer, fraction))
+    }
+}
+
+/// Returns the cost of the account storage in yoctoNEAR.
+pub fn storage_cost(storage_bytes: u64, storage_price_per_byte: u128) -> u128 {
+    (storage_bytes as u128).saturating_mul(storage_price_per_byte)
+}
+
+/// Returns the balance that the account can spend. The locked balance also covers the storage
+/// cost, so only the part of the storage cost above the locked balance is reserved.
+pub fn available_balance(balance: u128, locked: u128, storage_cost: u128) -> u128 {
+    balance.saturating_sub(storage_cost.saturating_sub(locked))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_decimal() {
+        assert_eq!(format_decimal("123", 0).as_deref(), Some("123"));
+        assert_eq!(format_decimal("5", 3).as_deref(), Some("0.005"));
+        assert_eq!(format_decimal("1230000", 6).as_deref(), Some("1.23"));
+        assert_eq!(format_decimal("1000000", 6).as_deref(), Some("1"));
+        assert_eq!(format_decimal("0", 24).as_deref(), Some("0"));
+        assert_eq!(
+            format_decimal(&u128::MAX.to_string(), 24).as_deref(),
+            Some("340282366920938.463463374607431768211455")
+        );
+        assert_eq!(format_decimal("-1", 6), None);
+        assert_eq!(format_decimal("1.5", 6), None);
+    }
+
+    #[test]
+    fn test_storage_cost() {
+        assert_eq!(storage_cost(100, 10), 1000);
+        assert_eq!(storage_cost(u64::MAX, u128::MAX), u128::MAX);
+    }
+
+    #[test]
+    fn test_available_balance() {
+        // The storage cost above the locked balance is reserved.
+        assert_eq!(available_balance(1000, 100, 300), 800);
+        // The locked balance covers the whole storage cost.
+        assert_eq!(available_balance(1000, 300, 300), 1000);
+        assert_eq!(available_balance(1000, 500, 300), 1000);
+        // The storage cost can't make the balance negative.
+        assert_eq!(available_balance(100, 0, 300), 0);
+        assert_eq!(available_balance(u128::MAX, 0, 0), u128::MAX);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{database, rpc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+
+const TARGET_CACHE: &str = "cache";
+
+/// Returns the cached values for the given keys, calling `fetch` with the indices of the keys that
+/// are missing from the cache. Fetched values are cached for `ttl_sec`, and values that `fetch`
+/// couldn't resolve are cached as empty strings for `missing_ttl_sec` to avoid retrying them on
+/// every request. RPC failures are logged and the affected keys are returned as `None`.
+pub(crate) async fn get_or_fetch<T, F, Fut>(
+    connection: &mut redis::aio::MultiplexedConnection,
+    keys: &[String],
+    ttl_sec: u64,
+    missing_ttl_sec: u64,
+    fetch: F,
+) -> Result<Vec<Option<T>>, database::DatabaseError>
+where
+    T: Serialize + DeserializeOwned,
+    F: FnOnce(Vec<usize>) -> Fut,
+    Fut: Future<Output = Result<Vec<Option<T>>, rpc::RpcError>>,
+{
+    let cached = database::query_mget(connection, keys).await?;
+
+    let mut values = Vec::with_capacity(keys.len());
+    let mut missing = vec![];
+    for (i, value) in cached.into_iter().enumerate() {
+        match value {
+            Some(value) => values.push(serde_json::from_str(&value).ok()),
+            None => {
+                values.push(None);
+                missing.push(i);
+            }
+        }
+    }
+    if missing.is_empty() {
+        return Ok(values);
+    }
+
+    match fetch(missing.clone()).await {
+        Ok(fetched) => {
+            let entries = missing
+                .iter()
+                .zip(fetched.iter())
+                .map(|(&i, value)| match value {
+                    Some(value) => (
+                        keys[i].clone(),
+                        serde_json::to_string(value).expect("Failed to serialize cached value"),
+                        ttl_sec,
+                    ),
+                    None => (keys[i].clone(), "".to_string(), missing_ttl_sec),
+                })
+                .collect::<Vec<_>>();
+            database::set_many_with_expiry(connection, &entries).await?;
+            for (i, value) in missing.into_iter().zip(fetched) {
+                values[i] = value;
+            }
+        }
+        Err(err) => {
+            tracing::error!(target: TARGET_CACHE, "Failed to fetch {} missing keys, e.g. {}: {:?}",
+                missing.len(),
+                keys[missing[0]],
+                err);
+        }
+    }
+
+    Ok(values)
+}
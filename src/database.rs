@@ -1,6 +1,12 @@
 use crate::api::BlockHeight;
+use crate::single_flight::SingleFlight;
+use std::sync::LazyLock;
 
 const TARGET_DB: &str = "database";
+/// The maximum number of fields requested by a single `HMGET` command.
+const BALANCES_CHUNK_SIZE: usize = 1000;
+/// The `COUNT` hint of the `SCAN` and `HSCAN` commands used by `scan_all` and `hscan_all`.
+const SCAN_BATCH_SIZE: usize = 1000;
 
 #[derive(Debug)]
 pub enum DatabaseError {
@@ -73,29 +79,57 @@ pub(crate) async fn query_zset_by_score(
     Ok(res?)
 }
 
+/// Returns the balances for the given `(token_id, account_id)` pairs in the same order. Pairs are
+/// grouped by token into `HMGET` commands on `b:{token_id}` of at most `BALANCES_CHUNK_SIZE`
+/// accounts each, sent in a single pipeline.
 pub(crate) async fn query_balances(
     connection: &mut redis::aio::MultiplexedConnection,
     pairs: &[(&str, &str)],
 ) -> Result<Vec<Option<String>>, DatabaseError> {
     let start = std::time::Instant::now();
 
+    let mut tokens: Vec<(&str, Vec<usize>)> = vec![];
+    for (i, (token_id, _)) in pairs.iter().enumerate() {
+        match tokens.iter_mut().find(|(t, _)| t == token_id) {
+            Some((_, indices)) => indices.push(i),
+            None => tokens.push((token_id, vec![i])),
+        }
+    }
+
     let mut pipe = redis::pipe();
-    for (token_id, account_id) in pairs {
-        pipe.cmd("HGET")
-            .arg(format!("b:{}", token_id))
-            .arg(account_id);
+    let mut chunks = vec![];
+    for (token_id, indices) in &tokens {
+        for chunk in indices.chunks(BALANCES_CHUNK_SIZE) {
+            let cmd = pipe.cmd("HMGET").arg(format!("b:{}", token_id));
+            for &i in chunk {
+                cmd.arg(pairs[i].1);
+            }
+            chunks.push(chunk);
+        }
     }
 
-    let res: redis::RedisResult<Vec<Option<String>>> = pipe.query_async(connection).await;
+    let res: Vec<Vec<Option<String>>> = if chunks.is_empty() {
+        vec![]
+    } else {
+        pipe.query_async(connection).await?
+    };
+
+    let mut balances = vec![None; pairs.len()];
+    for (chunk, values) in chunks.into_iter().zip(res) {
+        for (&i, value) in chunk.iter().zip(values) {
+            balances[i] = value;
+        }
+    }
 
     let duration = start.elapsed().as_millis();
 
-    tracing::debug!(target: TARGET_DB, "Query {}ms: query_balances {} pairs",
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_balances {} pairs on {} tokens",
         duration,
-        pairs.len()
+        pairs.len(),
+        tokens.len()
     );
 
-    Ok(res?)
+    Ok(balances)
 }
 
 pub(crate) async fn query_hget(
@@ -137,3 +171,726 @@ pub(crate) async fn query_get(
 
     Ok(res?)
 }
+
+async fn scan_keys(
+    connection: &mut redis::aio::MultiplexedConnection,
+    cursor: u64,
+    pattern: &str,
+    count: usize,
+) -> Result<(u64, Vec<String>), DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let res: redis::RedisResult<(u64, Vec<String>)> = redis::cmd("SCAN")
+        .arg(cursor)
+        .arg("MATCH")
+        .arg(pattern)
+        .arg("COUNT")
+        .arg(count)
+        .query_async(connection)
+        .await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: scan_keys {} {}",
+        duration,
+        pattern,
+        cursor);
+
+    Ok(res?)
+}
+
+/// Scans all keys matching the pattern and folds every batch of keys into the state with `f`.
+/// The state is passed by value, so `f` can run queries on its own clone of the connection.
+pub(crate) async fn scan_all<S, F, Fut>(
+    connection: &mut redis::aio::MultiplexedConnection,
+    pattern: &str,
+    init: S,
+    mut f: F,
+) -> Result<S, DatabaseError>
+where
+    F: FnMut(redis::aio::MultiplexedConnection, S, Vec<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<S, DatabaseError>>,
+{
+    let mut state = init;
+    let mut cursor = 0;
+    loop {
+        let (next_cursor, keys) = scan_keys(connection, cursor, pattern, SCAN_BATCH_SIZE).await?;
+        if !keys.is_empty() {
+            state = f(connection.clone(), state, keys).await?;
+        }
+        if next_cursor == 0 {
+            return Ok(state);
+        }
+        cursor = next_cursor;
+    }
+}
+
+pub(crate) async fn query_hgetall_many(
+    connection: &mut redis::aio::MultiplexedConnection,
+    keys: &[String],
+) -> Result<Vec<Vec<(String, String)>>, DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.cmd("HGETALL").arg(key);
+    }
+
+    let res: redis::RedisResult<Vec<Vec<(String, String)>>> = pipe.query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_hgetall_many {} keys",
+        duration,
+        keys.len());
+
+    Ok(res?)
+}
+
+/// Atomically replaces the hash at `key` with the given fields by writing them into a temporary
+/// key first and renaming it over the old one.
+pub(crate) async fn replace_hash(
+    connection: &mut redis::aio::MultiplexedConnection,
+    key: &str,
+    fields: &[(String, String)],
+) -> Result<(), DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let tmp_key = format!("{}:tmp", key);
+    let mut pipe = redis::pipe();
+    pipe.cmd("DEL").arg(&tmp_key).ignore();
+    for chunk in fields.chunks(1000) {
+        pipe.cmd("HSET").arg(&tmp_key).arg(chunk).ignore();
+    }
+    if fields.is_empty() {
+        pipe.cmd("DEL").arg(key).ignore();
+    } else {
+        pipe.cmd("RENAME").arg(&tmp_key).arg(key).ignore();
+    }
+
+    let res: redis::RedisResult<()> = pipe.query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: replace_hash {} with {} fields",
+        duration,
+        key,
+        fields.len());
+
+    Ok(res?)
+}
+
+pub(crate) async fn query_mget(
+    connection: &mut redis::aio::MultiplexedConnection,
+    keys: &[String],
+) -> Result<Vec<Option<String>>, DatabaseError> {
+    if keys.is_empty() {
+        return Ok(vec![]);
+    }
+    let start = std::time::Instant::now();
+
+    let res: redis::RedisResult<Vec<Option<String>>> =
+        redis::cmd("MGET").arg(keys).query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_mget {} keys",
+        duration,
+        keys.len());
+
+    Ok(res?)
+}
+
+pub(crate) async fn set_many_with_expiry(
+    connection: &mut redis::aio::MultiplexedConnection,
+    entries: &[(String, String, u64)],
+) -> Result<(), DatabaseError> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let start = std::time::Instant::now();
+
+    let mut pipe = redis::pipe();
+    for (key, value, expiry_sec) in entries {
+        pipe.cmd("SET")
+            .arg(key)
+            .arg(value)
+            .arg("EX")
+            .arg(expiry_sec)
+            .ignore();
+    }
+
+    let res: redis::RedisResult<()> = pipe.query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: set_many_with_expiry {} keys",
+        duration,
+        entries.len());
+
+    Ok(res?)
+}
+
+pub(crate) async fn hset_many(
+    connection: &mut redis::aio::MultiplexedConnection,
+    entries: &[(String, String, String)],
+) -> Result<(), DatabaseError> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let start = std::time::Instant::now();
+
+    let mut pipe = redis::pipe();
+    for (key, field, value) in entries {
+        pipe.cmd("HSET").arg(key).arg(field).arg(value).ignore();
+    }
+
+    let res: redis::RedisResult<()> = pipe.query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: hset_many {} entries",
+        duration,
+        entries.len());
+
+    Ok(res?)
+}
+
+/// Returns the first `limit` members of the sorted set by rank.
+pub(crate) async fn query_zrange_by_rank(
+    connection: &mut redis::aio::MultiplexedConnection,
+    key: &str,
+    limit: usize,
+) -> Result<Vec<String>, DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let res: redis::RedisResult<Vec<String>> = redis::cmd("ZRANGE")
+        .arg(key)
+        .arg(0)
+        .arg(limit as i64 - 1)
+        .query_async(connection)
+        .await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_zrange_by_rank {} {}",
+        duration,
+        key,
+        limit);
+
+    Ok(res?)
+}
+
+/// Returns up to `limit` members starting with the prefix from a sorted set where all members have
+/// the same score, in lexicographical order.
+pub(crate) async fn query_zrange_by_lex_prefix(
+    connection: &mut redis::aio::MultiplexedConnection,
+    key: &str,
+    prefix: &str,
+    limit: usize,
+) -> Result<Vec<String>, DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let mut min = b"[".to_vec();
+    min.extend_from_slice(prefix.as_bytes());
+    let mut max = min.clone();
+    max.push(0xff);
+    let res: redis::RedisResult<Vec<String>> = redis::cmd("ZRANGE")
+        .arg(key)
+        .arg(min)
+        .arg(max)
+        .arg("BYLEX")
+        .arg("LIMIT")
+        .arg(0)
+        .arg(limit)
+        .query_async(connection)
+        .await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_zrange_by_lex_prefix {} {}",
+        duration,
+        key,
+        prefix);
+
+    Ok(res?)
+}
+
+pub(crate) async fn query_hmget(
+    connection: &mut redis::aio::MultiplexedConnection,
+    key: &str,
+    fields: &[String],
+) -> Result<Vec<Option<String>>, DatabaseError> {
+    if fields.is_empty() {
+        return Ok(vec![]);
+    }
+    let start = std::time::Instant::now();
+
+    let res: redis::RedisResult<Vec<Option<String>>> = redis::cmd("HMGET")
+        .arg(key)
+        .arg(fields)
+        .query_async(connection)
+        .await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_hmget {} {} fields",
+        duration,
+        key,
+        fields.len());
+
+    Ok(res?)
+}
+
+pub(crate) async fn query_hget_many(
+    connection: &mut redis::aio::MultiplexedConnection,
+    pairs: &[(String, &str)],
+) -> Result<Vec<Option<String>>, DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let mut pipe = redis::pipe();
+    for (key, field) in pairs {
+        pipe.cmd("HGET").arg(key).arg(field);
+    }
+
+    let res: redis::RedisResult<Vec<Option<String>>> = pipe.query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_hget_many {} pairs",
+        duration,
+        pairs.len()
+    );
+
+    Ok(res?)
+}
+
+pub(crate) async fn delete_keys(
+    connection: &mut redis::aio::MultiplexedConnection,
+    keys: &[String],
+) -> Result<(), DatabaseError> {
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let start = std::time::Instant::now();
+
+    let res: redis::RedisResult<()> = redis::cmd("DEL").arg(keys).query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: delete_keys {} keys",
+        duration,
+        keys.len());
+
+    Ok(res?)
+}
+
+pub(crate) async fn exists_many(
+    connection: &mut redis::aio::MultiplexedConnection,
+    keys: &[String],
+) -> Result<Vec<bool>, DatabaseError> {
+    if keys.is_empty() {
+        return Ok(vec![]);
+    }
+    let start = std::time::Instant::now();
+
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.cmd("EXISTS").arg(key);
+    }
+
+    let res: redis::RedisResult<Vec<bool>> = pipe.query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: exists_many {} keys",
+        duration,
+        keys.len());
+
+    Ok(res?)
+}
+
+/// Moves every `(source, target)` key, replacing the target. Uses `COPY ... REPLACE` and `DEL`
+/// instead of `RENAME`, so a source that was already moved is skipped instead of failing.
+pub(crate) async fn move_keys(
+    connection: &mut redis::aio::MultiplexedConnection,
+    keys: &[(String, String)],
+) -> Result<(), DatabaseError> {
+    if keys.is_empty() {
+        return Ok(());
+    }
+    let start = std::time::Instant::now();
+
+    let mut pipe = redis::pipe();
+    for (source, target) in keys {
+        pipe.cmd("COPY")
+            .arg(source)
+            .arg(target)
+            .arg("REPLACE")
+            .ignore();
+        pipe.cmd("DEL").arg(source).ignore();
+    }
+
+    let res: redis::RedisResult<()> = pipe.query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: move_keys {} keys",
+        duration,
+        keys.len());
+
+    Ok(res?)
+}
+
+async fn hscan(
+    connection: &mut redis::aio::MultiplexedConnection,
+    key: &str,
+    cursor: u64,
+    count: usize,
+) -> Result<(u64, Vec<(String, String)>), DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let res: redis::RedisResult<(u64, Vec<(String, String)>)> = redis::cmd("HSCAN")
+        .arg(key)
+        .arg(cursor)
+        .arg("COUNT")
+        .arg(count)
+        .query_async(connection)
+        .await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: hscan {} {}",
+        duration,
+        key,
+        cursor);
+
+    Ok(res?)
+}
+
+/// Scans all fields of the hash and calls `f` with every batch of `(field, value)` pairs.
+pub(crate) async fn hscan_all<F>(
+    connection: &mut redis::aio::MultiplexedConnection,
+    key: &str,
+    mut f: F,
+) -> Result<(), DatabaseError>
+where
+    F: FnMut(Vec<(String, String)>),
+{
+    let mut cursor = 0;
+    loop {
+        let (next_cursor, fields) = hscan(connection, key, cursor, SCAN_BATCH_SIZE).await?;
+        f(fields);
+        if next_cursor == 0 {
+            return Ok(());
+        }
+        cursor = next_cursor;
+    }
+}
+
+/// Atomically replaces the sorted set at `key` with the given members, similar to `replace_hash`.
+pub(crate) async fn replace_zset(
+    connection: &mut redis::aio::MultiplexedConnection,
+    key: &str,
+    members: &[(f64, String)],
+) -> Result<(), DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let tmp_key = format!("{}:tmp", key);
+    let mut pipe = redis::pipe();
+    pipe.cmd("DEL").arg(&tmp_key).ignore();
+    for chunk in members.chunks(1000) {
+        pipe.cmd("ZADD").arg(&tmp_key).arg(chunk).ignore();
+    }
+    if members.is_empty() {
+        pipe.cmd("DEL").arg(key).ignore();
+    } else {
+        pipe.cmd("RENAME").arg(&tmp_key).arg(key).ignore();
+    }
+
+    let res: redis::RedisResult<()> = pipe.query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: replace_zset {} with {} members",
+        duration,
+        key,
+        members.len());
+
+    Ok(res?)
+}
+
+/// Returns the balances of the accounts for every token from the `b:{token_id}` hashes, and
+/// whether every account has every NFT contract in its `nf:{account_id}` hash, in a single
+/// pipeline.
+pub(crate) async fn query_holdings(
+    connection: &mut redis::aio::MultiplexedConnection,
+    account_ids: &[String],
+    token_ids: &[&str],
+    contract_ids: &[&str],
+) -> Result<(Vec<Vec<Option<String>>>, Vec<Vec<bool>>), DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let mut pipe = redis::pipe();
+    if !account_ids.is_empty() {
+        for token_id in token_ids {
+            pipe.cmd("HMGET")
+                .arg(format!("b:{}", token_id))
+                .arg(account_ids);
+        }
+    }
+    for account_id in account_ids {
+        for contract_id in contract_ids {
+            pipe.cmd("HEXISTS")
+                .arg(format!("nf:{}", account_id))
+                .arg(contract_id);
+        }
+    }
+
+    let res: redis::RedisResult<Vec<redis::Value>> = pipe.query_async(connection).await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_holdings {} accounts, {} tokens, {} contracts",
+        duration,
+        account_ids.len(),
+        token_ids.len(),
+        contract_ids.len());
+
+    let mut values = res?.into_iter();
+    let balances = if account_ids.is_empty() {
+        token_ids.iter().map(|_| vec![]).collect()
+    } else {
+        values
+            .by_ref()
+            .take(token_ids.len())
+            .map(|v| redis::from_redis_value(&v))
+            .collect::<redis::RedisResult<Vec<Vec<Option<String>>>>>()?
+    };
+    let owned = values
+        .map(|v| redis::from_redis_value(&v))
+        .collect::<redis::RedisResult<Vec<bool>>>()?;
+    let owned = if contract_ids.is_empty() {
+        account_ids.iter().map(|_| vec![]).collect()
+    } else {
+        owned
+            .chunks(contract_ids.len())
+            .map(|chunk| chunk.to_vec())
+            .collect()
+    };
+
+    Ok((balances, owned))
+}
+
+/// Reads the indexed data of an account atomically, so all parts correspond to the same sync block.
+/// KEYS: `st:{account_id}`, `ft:{account_id}`, `nf:{account_id}`, `accounts`, `meta:latest_block`.
+/// ARGV: the account ID.
+/// The `b:{token_id}` balance keys depend on the tokens, so the script reads them without declaring
+/// them in KEYS. This requires a non-cluster Redis, where all keys are on the same node.
+/// Returns the staking pools, tokens, token balances in the order of tokens, NFT contracts,
+/// account state and the latest block.
+static ACCOUNT_SNAPSHOT_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r#"
+local account_id = ARGV[1]
+local pools = redis.call('HGETALL', KEYS[1])
+local tokens = redis.call('HGETALL', KEYS[2])
+local balances = {}
+for i = 1, #tokens, 2 do
+    balances[#balances + 1] = redis.call('HGET', 'b:' .. tokens[i], account_id)
+end
+local nfts = redis.call('HGETALL', KEYS[3])
+local state = redis.call('HGET', KEYS[4], account_id)
+local latest_block = redis.call('GET', KEYS[5])
+return {pools, tokens, balances, nfts, state, latest_block}
+"#,
+    )
+});
+
+#[derive(Clone)]
+pub(crate) struct AccountSnapshot {
+    pub pools: Vec<(String, Option<BlockHeight>)>,
+    /// Tokens with their last update block height and balance.
+    pub tokens: Vec<(String, Option<BlockHeight>, Option<String>)>,
+    pub nfts: Vec<(String, Option<BlockHeight>)>,
+    pub state: Option<String>,
+    pub latest_block: Option<BlockHeight>,
+}
+
+static ACCOUNT_SNAPSHOT_FLIGHTS: LazyLock<SingleFlight<AccountSnapshot>> =
+    LazyLock::new(SingleFlight::new);
+
+/// Returns a consistent snapshot of the account's staking pools, tokens with balances, NFT
+/// contracts and state in a single round-trip, along with the `meta:latest_block` it corresponds to.
+/// Identical concurrent queries are coalesced.
+pub(crate) async fn query_account_snapshot(
+    connection: &mut redis::aio::MultiplexedConnection,
+    account_id: &str,
+) -> Result<AccountSnapshot, DatabaseError> {
+    ACCOUNT_SNAPSHOT_FLIGHTS
+        .run(account_id.to_string(), || {
+            fetch_account_snapshot(connection, account_id)
+        })
+        .await
+}
+
+async fn fetch_account_snapshot(
+    connection: &mut redis::aio::MultiplexedConnection,
+    account_id: &str,
+) -> Result<AccountSnapshot, DatabaseError> {
+    let start = std::time::Instant::now();
+
+    type SnapshotResult = (
+        Vec<(String, String)>,
+        Vec<(String, String)>,
+        Vec<Option<String>>,
+        Vec<(String, String)>,
+        Option<String>,
+        Option<String>,
+    );
+    let res: redis::RedisResult<SnapshotResult> = ACCOUNT_SNAPSHOT_SCRIPT
+        .key(format!("st:{}", account_id))
+        .key(format!("ft:{}", account_id))
+        .key(format!("nf:{}", account_id))
+        .key("accounts")
+        .key("meta:latest_block")
+        .arg(account_id)
+        .invoke_async(connection)
+        .await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_account_snapshot {}",
+        duration,
+        account_id);
+
+    let (pools, tokens, balances, nfts, state, latest_block) = res?;
+    let parse = |entries: Vec<(String, String)>| {
+        entries
+            .into_iter()
+            .map(|(k, v)| (k, v.parse().ok()))
+            .collect::<Vec<_>>()
+    };
+
+    Ok(AccountSnapshot {
+        pools: parse(pools),
+        tokens: tokens
+            .into_iter()
+            .zip(balances)
+            .map(|((token_id, v), balance)| (token_id, v.parse().ok(), balance))
+            .collect(),
+        nfts: parse(nfts),
+        state,
+        latest_block: latest_block.and_then(|h| h.parse().ok()),
+    })
+}
+
+/// Returns the top holders of a token with exact balances, ordered by balance and then by account
+/// ID, both descending.
+/// KEYS: `tb:{token_id}`, `b:{token_id}`.
+/// ARGV: the limit, the maximum number of candidates.
+/// The `tb:` scores are f64 approximations of the u128 balances. The rounding is monotonic, so
+/// holders can only be misordered within equal scores. All holders with the score at the boundary
+/// of the limit are taken as candidates (up to the maximum), and the candidates are ranked by
+/// their exact `b:` balances, compared as decimal strings by length and then lexicographically.
+static TOP_BALANCES_SCRIPT: LazyLock<redis::Script> = LazyLock::new(|| {
+    redis::Script::new(
+        r#"
+local limit = tonumber(ARGV[1])
+local top = redis.call('ZRANGE', KEYS[1], 'inf', 0, 'BYSCORE', 'REV', 'LIMIT', 0, limit, 'WITHSCORES')
+if #top == 0 then
+    return {}
+end
+local candidates = {}
+if #top < limit * 2 then
+    for i = 1, #top, 2 do
+        candidates[#candidates + 1] = top[i]
+    end
+else
+    candidates = redis.call('ZRANGE', KEYS[1], 'inf', top[#top], 'BYSCORE', 'REV', 'LIMIT', 0, tonumber(ARGV[2]))
+end
+local balances = redis.call('HMGET', KEYS[2], unpack(candidates))
+local holders = {}
+for i, account_id in ipairs(candidates) do
+    holders[i] = {account_id, balances[i]}
+end
+local function exact(balance)
+    if balance then
+        return balance
+    end
+    return '0'
+end
+table.sort(holders, function(a, b)
+    local x, y = exact(a[2]), exact(b[2])
+    if #x ~= #y then
+        return #x > #y
+    end
+    if x ~= y then
+        return x > y
+    end
+    return a[1] > b[1]
+end)
+local res = {}
+for i = 1, math.min(limit, #holders) do
+    res[#res + 1] = holders[i][1]
+    res[#res + 1] = holders[i][2]
+end
+return res
+"#,
+    )
+});
+
+/// Account IDs with their balances.
+type Holders = Vec<(String, Option<String>)>;
+
+static TOP_BALANCES_FLIGHTS: LazyLock<SingleFlight<Holders>> = LazyLock::new(SingleFlight::new);
+
+/// Returns the top `limit` holders of the token with their exact balances in a single consistent
+/// request. At most `max_candidates` holders are compared when scores are tied at the boundary.
+/// Identical concurrent queries are coalesced.
+pub(crate) async fn query_top_balances(
+    connection: &mut redis::aio::MultiplexedConnection,
+    token_id: &str,
+    limit: usize,
+    max_candidates: usize,
+) -> Result<Holders, DatabaseError> {
+    TOP_BALANCES_FLIGHTS
+        .run(format!("{}:{}:{}", token_id, limit, max_candidates), || {
+            fetch_top_balances(connection, token_id, limit, max_candidates)
+        })
+        .await
+}
+
+async fn fetch_top_balances(
+    connection: &mut redis::aio::MultiplexedConnection,
+    token_id: &str,
+    limit: usize,
+    max_candidates: usize,
+) -> Result<Vec<(String, Option<String>)>, DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let res: redis::RedisResult<Vec<(String, Option<String>)>> = TOP_BALANCES_SCRIPT
+        .key(format!("tb:{}", token_id))
+        .key(format!("b:{}", token_id))
+        .arg(limit)
+        .arg(max_candidates)
+        .invoke_async(connection)
+        .await;
+
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_DB, "Query {}ms: query_top_balances {} limit {}",
+        duration,
+        token_id,
+        limit);
+
+    Ok(res?)
+}
+
+/// Returns the request coalescing metrics of the queries.
+pub(crate) fn single_flight_metrics() -> serde_json::Value {
+    serde_json::json!({
+        "query_account_snapshot": ACCOUNT_SNAPSHOT_FLIGHTS.metrics(),
+        "query_top_balances": TOP_BALANCES_FLIGHTS.metrics(),
+    })
+}
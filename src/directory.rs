@@ -0,0 +1,82 @@
+use crate::api::BlockHeight;
+use crate::database;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const TARGET_DIRECTORY: &str = "directory";
+
+/// Directories are stored as `dir:{prefix}` hashes, e.g. `dir:ft` and `dir:nf`, mapping a contract
+/// ID to a JSON serialized `DirectoryEntry`. Each directory is indexed by two sorted sets:
+/// - `dir:{prefix}:holders` - scored by the negated number of holders, so the ranks are ordered by
+///   decreasing holders and then by contract ID.
+/// - `dir:{prefix}:lex` - all scored 0, for the contract ID prefix search.
+pub const DIRECTORY_PREFIX: &str = "dir";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct DirectoryEntry {
+    pub holders: u64,
+    pub last_update_block_height: Option<BlockHeight>,
+}
+
+/// Rebuilds the FT and NFT contract directories by scanning the per-account `ft:` and `nf:`
+/// hashes.
+pub async fn rebuild(
+    mut connection: redis::aio::MultiplexedConnection,
+) -> Result<(), database::DatabaseError> {
+    for prefix in ["ft", "nf"] {
+        let key = format!("{}:{}", DIRECTORY_PREFIX, prefix);
+        let start = std::time::Instant::now();
+        let entries = collect_entries(&mut connection, prefix).await?;
+        let by_holders = entries
+            .iter()
+            .map(|(contract_id, entry)| (-(entry.holders as f64), contract_id.clone()))
+            .collect::<Vec<_>>();
+        let by_contract_id = entries
+            .keys()
+            .map(|contract_id| (0.0, contract_id.clone()))
+            .collect::<Vec<_>>();
+        let fields = entries
+            .into_iter()
+            .map(|(contract_id, entry)| {
+                (
+                    contract_id,
+                    serde_json::to_string(&entry).expect("Failed to serialize directory entry"),
+                )
+            })
+            .collect::<Vec<_>>();
+        database::replace_hash(&mut connection, &key, &fields).await?;
+        database::replace_zset(&mut connection, &format!("{}:holders", key), &by_holders).await?;
+        database::replace_zset(&mut connection, &format!("{}:lex", key), &by_contract_id).await?;
+
+        tracing::info!(target: TARGET_DIRECTORY, "Rebuilt {} with {} contracts in {}ms",
+            key,
+            fields.len(),
+            start.elapsed().as_millis());
+    }
+
+    Ok(())
+}
+
+async fn collect_entries(
+    connection: &mut redis::aio::MultiplexedConnection,
+    prefix: &str,
+) -> Result<HashMap<String, DirectoryEntry>, database::DatabaseError> {
+    database::scan_all(
+        connection,
+        &format!("{}:*", prefix),
+        HashMap::new(),
+        |mut c, mut entries: HashMap<String, DirectoryEntry>, keys| async move {
+            for contracts in database::query_hgetall_many(&mut c, &keys).await? {
+                for (contract_id, last_update_block_height) in contracts {
+                    let entry = entries.entry(contract_id).or_default();
+                    entry.holders += 1;
+                    entry.last_update_block_height = entry
+                        .last_update_block_height
+                        .max(last_update_block_height.parse().ok());
+                }
+            }
+            Ok(entries)
+        },
+    )
+    .await
+}
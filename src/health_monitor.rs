@@ -0,0 +1,107 @@
+use crate::api::HealthError;
+use crate::status::{self, HealthThresholds, SyncStatus};
+use crate::Config;
+use reqwest::Client;
+use serde_json::json;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const TARGET_HEALTH_MONITOR: &str = "health_monitor";
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Continuously evaluates the health of the server with the default thresholds and alerts the
+/// webhooks on transitions between healthy and unhealthy. To avoid flapping, the state only changes
+/// after `checks_to_transition` consecutive checks with the opposite result.
+pub async fn run(
+    sync_status: tokio::sync::watch::Receiver<SyncStatus>,
+    config: Config,
+    interval: Duration,
+    checks_to_transition: u32,
+    webhook_urls: Vec<String>,
+) {
+    let client = Client::new();
+    let thresholds = HealthThresholds::from_config(&config);
+    let mut healthy = true;
+    // The number of consecutive checks with the opposite result of the current state, and the
+    // time of the first one.
+    let mut streak: u32 = 0;
+    let mut streak_start = SystemTime::now();
+    let mut last_error: Option<HealthError> = None;
+    let mut outage_start = SystemTime::now();
+
+    loop {
+        let res = status::is_healthy(
+            status::status_json(sync_status.borrow().clone()),
+            &config,
+            &thresholds,
+        );
+        if res.is_ok() == healthy {
+            streak = 0;
+        } else {
+            if streak == 0 {
+                streak_start = SystemTime::now();
+            }
+            streak += 1;
+        }
+        if let Err(err) = res {
+            last_error = Some(err);
+        }
+
+        if streak >= checks_to_transition {
+            streak = 0;
+            healthy = !healthy;
+            let alert = if healthy {
+                let outage_duration_sec = streak_start
+                    .duration_since(outage_start)
+                    .unwrap_or_default()
+                    .as_secs_f64();
+                tracing::info!(target: TARGET_HEALTH_MONITOR,
+                    status = "healthy",
+                    outage_duration_sec,
+                    last_error = ?last_error,
+                    "Health recovered after {:.1}s", outage_duration_sec);
+                json!({
+                    "status": "healthy",
+                    "timestamp": unix_timestamp(streak_start),
+                    "last_error": last_error.take(),
+                    "outage_duration_sec": outage_duration_sec,
+                })
+            } else {
+                outage_start = streak_start;
+                tracing::warn!(target: TARGET_HEALTH_MONITOR,
+                    status = "unhealthy",
+                    error = ?last_error,
+                    "Health degraded: {:?}", last_error);
+                json!({
+                    "status": "unhealthy",
+                    "timestamp": unix_timestamp(outage_start),
+                    "error": last_error,
+                })
+            };
+            // The alerts are sent in the background, so a slow webhook doesn't delay the checks.
+            for url in &webhook_urls {
+                tokio::spawn(send_alert(client.clone(), url.clone(), alert.clone()));
+            }
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn send_alert(client: Client, url: String, alert: serde_json::Value) {
+    if let Err(err) = client
+        .post(&url)
+        .json(&alert)
+        .timeout(WEBHOOK_TIMEOUT)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+    {
+        tracing::error!(target: TARGET_HEALTH_MONITOR, "Failed to send health alert to {}: {:?}", url, err);
+    }
+}
+
+fn unix_timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,10 @@
+use sha2::{Digest, Sha256};
+
+const LOCKUP_ACCOUNT_SUFFIX: &str = "lockup.near";
+
+/// Returns the lockup account ID of the owner, which is the first 40 hex characters of the
+/// SHA-256 hash of the owner account ID under `lockup.near`.
+pub fn lockup_account_id(owner_id: &str) -> String {
+    let hash = hex::encode(Sha256::digest(owner_id.as_bytes()));
+    format!("{}.{}", &hash[..40], LOCKUP_ACCOUNT_SUFFIX)
+}
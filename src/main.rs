@@ -1,10 +1,24 @@
 mod api;
+mod balance;
+mod cache;
 mod database;
+mod directory;
+mod health_monitor;
+mod lockup;
+mod metadata;
+mod near_top;
+mod nft;
+mod periodic;
 mod redis_db;
+mod reverse_index;
 mod rpc;
+mod single_flight;
+mod staking;
 mod status;
+mod status_history;
 
 use dotenv::dotenv;
+use periodic::spawn_periodic;
 use std::env;
 
 use actix_cors::Cors;
@@ -16,12 +30,21 @@ use tracing_subscriber::EnvFilter;
 pub struct Config {
     pub max_healthy_latency_sec: f64,
     pub max_healthy_sync_block_diff: u64,
+    pub storage_price_per_byte: u128,
+    pub subsystem_thresholds: std::collections::BTreeMap<String, status::SubsystemThreshold>,
+    pub health_profiles: std::collections::HashMap<String, status::HealthProfile>,
+    /// The bounds for the caller-provided `/health` thresholds.
+    pub min_health_latency_bound_sec: f64,
+    pub max_health_latency_bound_sec: f64,
+    pub max_health_block_diff_bound: u64,
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub redis_client: redis::Client,
     pub config: Config,
+    pub sync_status: tokio::sync::watch::Receiver<status::SyncStatus>,
+    pub status_history: std::sync::Arc<std::sync::Mutex<status_history::StatusHistory>>,
 }
 
 const INDEX_HTML: &str = include_str!("../index.html");
@@ -40,6 +63,22 @@ async fn skill_md() -> impl Responder {
         .body(SKILL_MD)
 }
 
+/// Returns the refresh interval of a background rebuild if it's enabled with `{enabled_var}=true`.
+/// The interval is read from `interval_var` in seconds, 600 by default.
+fn rebuild_interval(enabled_var: &str, interval_var: &str) -> Option<std::time::Duration> {
+    if env::var(enabled_var).ok() != Some("true".to_string()) {
+        return None;
+    }
+    Some(std::time::Duration::from_secs(
+        env::var(interval_var)
+            .map(|s| {
+                s.parse()
+                    .unwrap_or_else(|_| panic!("Failed to parse {}", interval_var))
+            })
+            .unwrap_or(600),
+    ))
+}
+
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     openssl_probe::init_ssl_cert_env_vars();
@@ -68,7 +107,125 @@ async fn main() -> std::io::Result<()> {
                     .expect("Failed to parse MAX_HEALTHY_SYNC_BLOCK_DIFF")
             })
             .unwrap_or(3),
+        storage_price_per_byte: env::var("STORAGE_PRICE_PER_BYTE")
+            .map(|s| s.parse().expect("Failed to parse STORAGE_PRICE_PER_BYTE"))
+            .unwrap_or(10_000_000_000_000_000_000),
+        subsystem_thresholds: env::var("SYNC_SUBSYSTEM_THRESHOLDS")
+            .map(|s| serde_json::from_str(&s).expect("Failed to parse SYNC_SUBSYSTEM_THRESHOLDS"))
+            .unwrap_or_default(),
+        health_profiles: env::var("HEALTH_PROFILES")
+            .map(|s| serde_json::from_str(&s).expect("Failed to parse HEALTH_PROFILES"))
+            .unwrap_or_default(),
+        min_health_latency_bound_sec: env::var("MIN_HEALTH_LATENCY_BOUND_SEC")
+            .map(|s| {
+                s.parse()
+                    .expect("Failed to parse MIN_HEALTH_LATENCY_BOUND_SEC")
+            })
+            .unwrap_or(1.0),
+        max_health_latency_bound_sec: env::var("MAX_HEALTH_LATENCY_BOUND_SEC")
+            .map(|s| {
+                s.parse()
+                    .expect("Failed to parse MAX_HEALTH_LATENCY_BOUND_SEC")
+            })
+            .unwrap_or(300.0),
+        max_health_block_diff_bound: env::var("MAX_HEALTH_BLOCK_DIFF_BOUND")
+            .map(|s| {
+                s.parse()
+                    .expect("Failed to parse MAX_HEALTH_BLOCK_DIFF_BOUND")
+            })
+            .unwrap_or(1000),
     };
+    assert!(
+        config.min_health_latency_bound_sec.is_finite()
+            && config.max_health_latency_bound_sec.is_finite()
+            && config.min_health_latency_bound_sec <= config.max_health_latency_bound_sec,
+        "MIN_HEALTH_LATENCY_BOUND_SEC and MAX_HEALTH_LATENCY_BOUND_SEC must be finite and MIN <= MAX"
+    );
+
+    let (sync_status_sender, sync_status) = tokio::sync::watch::channel(Default::default());
+    let status_interval = std::time::Duration::from_millis(
+        env::var("STATUS_POLL_INTERVAL_MS")
+            .map(|s| s.parse().expect("Failed to parse STATUS_POLL_INTERVAL_MS"))
+            .unwrap_or(1000),
+    );
+    let status_history = std::sync::Arc::new(std::sync::Mutex::new(
+        status_history::StatusHistory::new(status_interval),
+    ));
+    let sync_subsystems = env::var("SYNC_SUBSYSTEMS")
+        .unwrap_or_else(|_| status::DEFAULT_SYNC_SUBSYSTEMS.to_string())
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    tokio::spawn(status::run(
+        redis_client.clone(),
+        status_interval,
+        sync_subsystems,
+        sync_status_sender,
+        status_history.clone(),
+    ));
+
+    if env::var("HEALTH_MONITOR").ok() == Some("true".to_string()) {
+        let interval = std::time::Duration::from_secs(
+            env::var("HEALTH_MONITOR_INTERVAL_SEC")
+                .map(|s| {
+                    s.parse()
+                        .expect("Failed to parse HEALTH_MONITOR_INTERVAL_SEC")
+                })
+                .unwrap_or(5),
+        );
+        let checks_to_transition = env::var("HEALTH_MONITOR_CHECKS_TO_TRANSITION")
+            .map(|s| {
+                s.parse()
+                    .expect("Failed to parse HEALTH_MONITOR_CHECKS_TO_TRANSITION")
+            })
+            .unwrap_or(3);
+        assert!(
+            checks_to_transition >= 1,
+            "HEALTH_MONITOR_CHECKS_TO_TRANSITION must be at least 1"
+        );
+        let webhook_urls = env::var("HEALTH_WEBHOOK_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        tokio::spawn(health_monitor::run(
+            sync_status.clone(),
+            config.clone(),
+            interval,
+            checks_to_transition,
+            webhook_urls,
+        ));
+    }
+
+    if let Some(interval) = rebuild_interval("DIRECTORY_INDEXER", "DIRECTORY_REFRESH_INTERVAL_SEC")
+    {
+        spawn_periodic(
+            "directory",
+            redis_client.clone(),
+            interval,
+            directory::rebuild,
+        );
+    }
+    if let Some(interval) =
+        rebuild_interval("REVERSE_INDEXER", "REVERSE_INDEX_REFRESH_INTERVAL_SEC")
+    {
+        spawn_periodic(
+            "reverse indexes",
+            redis_client.clone(),
+            interval,
+            reverse_index::rebuild,
+        );
+    }
+    if let Some(interval) = rebuild_interval("NEAR_TOP_INDEXER", "NEAR_TOP_REFRESH_INTERVAL_SEC") {
+        spawn_periodic(
+            "top accounts",
+            redis_client.clone(),
+            interval,
+            near_top::rebuild,
+        );
+    }
 
     HttpServer::new(move || {
         // Configure CORS middleware
@@ -80,6 +237,7 @@ async fn main() -> std::io::Result<()> {
                 header::AUTHORIZATION,
                 header::ACCEPT,
             ])
+            .expose_headers(vec!["x-sync-block-height", "x-sync-latency-sec"])
             .max_age(3600)
             .supports_credentials();
 
@@ -104,15 +262,31 @@ async fn main() -> std::io::Result<()> {
             .service(api::v1::staking)
             .service(api::v1::ft)
             .service(api::v1::nft)
+            .service(api::v1::nft_tokens)
+            .service(api::v1::account_keys)
+            .service(api::v1::account_access_keys)
             .service(api::v1::ft_top)
-            .service(api::v1::account_full);
+            .service(api::v1::ft_storage)
+            .service(api::v1::ft_storage_batch)
+            .service(api::v1::ft_balances)
+            .service(api::v1::token_gate)
+            .service(api::v1::account_full)
+            .service(api::v1::public_key_portfolio)
+            .service(api::v1::ft_directory)
+            .service(api::v1::nft_directory)
+            .service(api::v1::staking_pool)
+            .service(api::v1::staking_pool_delegators)
+            .service(api::v1::near_top);
 
         App::new()
             .app_data(web::Data::new(AppState {
                 redis_client: redis_client.clone(),
                 config: config.clone(),
+                sync_status: sync_status.clone(),
+                status_history: status_history.clone(),
             }))
             .wrap(cors)
+            .wrap(middleware::from_fn(status::freshness_headers))
             .wrap(middleware::Logger::new(
                 "%{r}a \"%r\"	%s %b \"%{Referer}i\" \"%{User-Agent}i\" %T",
             ))
@@ -121,6 +295,7 @@ async fn main() -> std::io::Result<()> {
             .service(api_exp)
             .service(api_v1)
             .service(status::status)
+            .service(status::status_history)
             .service(status::health)
             .route("/index.html", web::get().to(index_html))
             .route("/skill.md", web::get().to(skill_md))
@@ -0,0 +1,90 @@
+use crate::rpc::{self, FtMetadata, NftContractMetadata};
+use crate::{cache, database};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// How long a successfully fetched metadata stays in the cache before it's refreshed from RPC.
+const METADATA_TTL_SEC: u64 = 60 * 60;
+/// How long to wait before retrying a contract that didn't return valid metadata.
+const MISSING_METADATA_TTL_SEC: u64 = 5 * 60;
+
+/// Returns the FT metadata for the given tokens from the `ftm:{token_id}` cache, fetching the
+/// missing entries through RPC.
+pub(crate) async fn get_ft_metadata(
+    connection: &mut redis::aio::MultiplexedConnection,
+    token_ids: &[&str],
+) -> Result<HashMap<String, Option<FtMetadata>>, database::DatabaseError> {
+    let keys = token_ids
+        .iter()
+        .map(|token_id| format!("ftm:{}", token_id))
+        .collect::<Vec<_>>();
+    let metadata = cache::get_or_fetch(
+        connection,
+        &keys,
+        METADATA_TTL_SEC,
+        MISSING_METADATA_TTL_SEC,
+        |missing| {
+            let token_ids = missing
+                .into_iter()
+                .map(|i| token_ids[i].to_string())
+                .collect::<Vec<_>>();
+            async move { rpc::get_ft_metadata(&token_ids).await }
+        },
+    )
+    .await?;
+
+    Ok(token_ids
+        .iter()
+        .map(|token_id| token_id.to_string())
+        .zip(metadata)
+        .collect())
+}
+
+/// Returns the NFT contract metadata for the given contracts from the `nftm:{contract_id}` cache,
+/// fetching the missing entries through RPC.
+pub(crate) async fn get_nft_metadata(
+    connection: &mut redis::aio::MultiplexedConnection,
+    contract_ids: &[&str],
+) -> Result<HashMap<String, Option<NftContractMetadata>>, database::DatabaseError> {
+    let keys = contract_ids
+        .iter()
+        .map(|contract_id| format!("nftm:{}", contract_id))
+        .collect::<Vec<_>>();
+    let metadata = cache::get_or_fetch(
+        connection,
+        &keys,
+        METADATA_TTL_SEC,
+        MISSING_METADATA_TTL_SEC,
+        |missing| {
+            let contract_ids = missing
+                .into_iter()
+                .map(|i| contract_ids[i].to_string())
+                .collect::<Vec<_>>();
+            async move { rpc::get_nft_metadata(&contract_ids).await }
+        },
+    )
+    .await?;
+
+    Ok(contract_ids
+        .iter()
+        .map(|contract_id| contract_id.to_string())
+        .zip(metadata)
+        .collect())
+}
+
+/// Returns the storage balance bounds of the token from the `ftsb:{token_id}` cache, fetching it
+/// through RPC if it's missing.
+pub(crate) async fn get_storage_balance_bounds(
+    connection: &mut redis::aio::MultiplexedConnection,
+    token_id: &str,
+) -> Result<Option<Value>, database::DatabaseError> {
+    let mut bounds = cache::get_or_fetch(
+        connection,
+        &[format!("ftsb:{}", token_id)],
+        METADATA_TTL_SEC,
+        MISSING_METADATA_TTL_SEC,
+        |_| async { rpc::get_storage_balance_bounds(&[token_id.to_string()]).await },
+    )
+    .await?;
+    Ok(bounds.pop().flatten())
+}
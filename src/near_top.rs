@@ -0,0 +1,48 @@
+use crate::database;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+const TARGET_NEAR_TOP: &str = "near_top";
+
+/// The sorted set of the accounts with the highest native balances. The scores are approximate,
+/// so the exact balances should be read from the `accounts` hash.
+pub const NEAR_TOP_KEY: &str = "top:near";
+/// The number of accounts to keep in the sorted set.
+const NEAR_TOP_SIZE: usize = 1000;
+
+/// Rebuilds the top accounts by native balance by scanning the `accounts` hash.
+pub async fn rebuild(
+    mut connection: redis::aio::MultiplexedConnection,
+) -> Result<(), database::DatabaseError> {
+    let start = std::time::Instant::now();
+
+    let mut top: BinaryHeap<Reverse<(u128, String)>> = BinaryHeap::new();
+    database::hscan_all(&mut connection, "accounts", |accounts| {
+        for (account_id, state) in accounts {
+            let Some(balance) = serde_json::from_str::<serde_json::Value>(&state)
+                .ok()
+                .and_then(|state| state["b"].as_str()?.parse::<u128>().ok())
+            else {
+                continue;
+            };
+            top.push(Reverse((balance, account_id)));
+            if top.len() > NEAR_TOP_SIZE {
+                top.pop();
+            }
+        }
+    })
+    .await?;
+
+    let members = top
+        .into_iter()
+        .map(|Reverse((balance, account_id))| (balance as f64, account_id))
+        .collect::<Vec<_>>();
+    database::replace_zset(&mut connection, NEAR_TOP_KEY, &members).await?;
+
+    tracing::info!(target: TARGET_NEAR_TOP, "Rebuilt {} with {} accounts in {}ms",
+        NEAR_TOP_KEY,
+        members.len(),
+        start.elapsed().as_millis());
+
+    Ok(())
+}
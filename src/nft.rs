@@ -0,0 +1,114 @@
+use crate::api::BlockHeight;
+use crate::{api, cache, database, rpc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Cached tokens don't depend on time, because the key includes the last update block height of
+/// the contract for the account. The expiry only bounds the cache size.
+const TOKENS_TTL_SEC: u64 = 60 * 60;
+/// How long to wait before retrying a contract that didn't return the owned tokens.
+const MISSING_TTL_SEC: u64 = 5 * 60;
+
+#[derive(Serialize, Deserialize)]
+pub struct NftTokens {
+    pub total: Option<u64>,
+    pub tokens: Option<Vec<Value>>,
+}
+
+/// Returns a page of NFT tokens owned by the account on the given contract. The result is cached in
+/// `nftt:{contract_id}:{account_id}:{last_update_block_height}:{from_index}:{limit}`, so any new
+/// change of the account tokens on this contract invalidates the cache.
+pub(crate) async fn get_tokens_for_owner(
+    connection: &mut redis::aio::MultiplexedConnection,
+    contract_id: &str,
+    account_id: &str,
+    last_update_block_height: Option<BlockHeight>,
+    from_index: u64,
+    limit: u64,
+) -> Result<NftTokens, api::ServiceError> {
+    let key = format!(
+        "nftt:{}:{}:{}:{}:{}",
+        contract_id,
+        account_id,
+        last_update_block_height
+            .map(|h| h.to_string())
+            .unwrap_or_default(),
+        from_index,
+        limit
+    );
+    if let Some(cached) = database::query_get(connection, &key)
+        .await?
+        .and_then(|v| serde_json::from_str(&v).ok())
+    {
+        return Ok(cached);
+    }
+
+    let (tokens, total) =
+        rpc::get_nft_tokens_for_owner(contract_id, account_id, from_index, limit).await?;
+    let res = NftTokens {
+        total,
+        tokens: tokens.map(|tokens| {
+            tokens
+                .into_iter()
+                .map(|token| {
+                    json!({
+                        "token_id": token["token_id"],
+                        "metadata": token["metadata"],
+                    })
+                })
+                .collect()
+        }),
+    };
+    let ttl_sec = if res.tokens.is_none() && res.total.is_none() {
+        MISSING_TTL_SEC
+    } else {
+        TOKENS_TTL_SEC
+    };
+    database::set_many_with_expiry(
+        connection,
+        &[(
+            key,
+            serde_json::to_string(&res).expect("Failed to serialize NFT tokens"),
+            ttl_sec,
+        )],
+    )
+    .await?;
+
+    Ok(res)
+}
+
+/// Returns the number of tokens owned by the account on each of the given contracts. Similar to
+/// the tokens, the counts are cached in `nfts:{contract_id}:{account_id}:{last_update_block_height}`.
+pub(crate) async fn get_supply_for_owner(
+    connection: &mut redis::aio::MultiplexedConnection,
+    account_id: &str,
+    contracts: &[(String, Option<BlockHeight>)],
+) -> Result<Vec<Option<u64>>, database::DatabaseError> {
+    let keys = contracts
+        .iter()
+        .map(|(contract_id, last_update_block_height)| {
+            format!(
+                "nfts:{}:{}:{}",
+                contract_id,
+                account_id,
+                last_update_block_height
+                    .map(|h| h.to_string())
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    cache::get_or_fetch(
+        connection,
+        &keys,
+        TOKENS_TTL_SEC,
+        MISSING_TTL_SEC,
+        |missing| {
+            let contract_ids = missing
+                .into_iter()
+                .map(|i| contracts[i].0.clone())
+                .collect::<Vec<_>>();
+            async move { rpc::get_nft_supply_for_owner(account_id, &contract_ids).await }
+        },
+    )
+    .await
+}
@@ -0,0 +1,30 @@
+use crate::database;
+use std::future::Future;
+use std::time::Duration;
+
+const TARGET_PERIODIC: &str = "periodic";
+
+/// Spawns a background task that runs `rebuild` with a new Redis connection every `interval`.
+/// A failed rebuild is logged and retried after the interval.
+pub fn spawn_periodic<F, Fut>(
+    name: &'static str,
+    redis_client: redis::Client,
+    interval: Duration,
+    rebuild: F,
+) where
+    F: Fn(redis::aio::MultiplexedConnection) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), database::DatabaseError>> + Send,
+{
+    tokio::spawn(async move {
+        loop {
+            let res = match redis_client.get_multiplexed_async_connection().await {
+                Ok(connection) => rebuild(connection).await,
+                Err(err) => Err(err.into()),
+            };
+            if let Err(err) = res {
+                tracing::error!(target: TARGET_PERIODIC, "Failed to rebuild {}: {:?}", name, err);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
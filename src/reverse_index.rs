@@ -0,0 +1,235 @@
+use crate::database;
+
+const TARGET_REVERSE_INDEX: &str = "reverse_index";
+
+/// Rebuilds the reverse indexes from the per-account hashes:
+/// - `sd:{pool_id}` - delegators of a staking pool from the `st:{account_id}` hashes.
+/// - `ak:{account_id}` - public keys of an account from the `pk:{public_key}` hashes.
+pub async fn rebuild(
+    mut connection: redis::aio::MultiplexedConnection,
+) -> Result<(), database::DatabaseError> {
+    rebuild_index(&mut connection, "st", "sd").await?;
+    rebuild_index(&mut connection, "pk", "ak").await?;
+
+    Ok(())
+}
+
+/// The keys a reverse index is rebuilt from and into. Every step of the rebuild is planned by these
+/// methods, so `rebuild_index` only runs the queries.
+struct IndexRebuild<'a> {
+    source_prefix: &'a str,
+    target_prefix: &'a str,
+    /// The prefix of the temporary keys the index is rebuilt into, e.g. `sd_tmp` for `sd`.
+    tmp_prefix: String,
+}
+
+/// Returns the part of the key after the prefix and the `:` separator.
+fn key_suffix<'a>(key: &'a str, prefix: &str) -> &'a str {
+    &key[prefix.len() + 1..]
+}
+
+impl<'a> IndexRebuild<'a> {
+    fn new(source_prefix: &'a str, target_prefix: &'a str) -> Self {
+        Self {
+            source_prefix,
+            target_prefix,
+            tmp_prefix: format!("{}_tmp", target_prefix),
+        }
+    }
+
+    /// Inverts a batch of `{source_prefix}:{account_id}` hashes of `field -> value` into
+    /// `(key, account_id, value)` entries of the `{tmp_prefix}:{field}` hashes.
+    fn writes(
+        &self,
+        source_keys: &[String],
+        values: Vec<Vec<(String, String)>>,
+    ) -> Vec<(String, String, String)> {
+        source_keys
+            .iter()
+            .zip(values)
+            .flat_map(|(key, fields)| {
+                let account_id = key_suffix(key, self.source_prefix);
+                fields.into_iter().map(move |(field, value)| {
+                    (
+                        format!("{}:{}", self.tmp_prefix, field),
+                        account_id.to_string(),
+                        value,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the temporary key the target key is rebuilt into.
+    fn tmp_key(&self, target_key: &str) -> String {
+        format!(
+            "{}:{}",
+            self.tmp_prefix,
+            key_suffix(target_key, self.target_prefix)
+        )
+    }
+
+    /// Returns the target keys to delete, given whether the temporary key of each exists. A target
+    /// key without a temporary key has no source entries left, so it wouldn't be replaced by a move.
+    fn stale_targets(&self, target_keys: Vec<String>, tmp_exists: &[bool]) -> Vec<String> {
+        target_keys
+            .into_iter()
+            .zip(tmp_exists)
+            .filter(|(_, exists)| !**exists)
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    /// Returns the `(tmp_key, target_key)` pairs to move the rebuilt temporary keys over the
+    /// target keys.
+    fn moves(&self, tmp_keys: Vec<String>) -> Vec<(String, String)> {
+        tmp_keys
+            .into_iter()
+            .map(|key| {
+                let target = format!(
+                    "{}:{}",
+                    self.target_prefix,
+                    key_suffix(&key, &self.tmp_prefix)
+                );
+                (key, target)
+            })
+            .collect()
+    }
+}
+
+/// Inverts all `{source_prefix}:{account_id}` hashes of `field -> value` into
+/// `{target_prefix}:{field}` hashes of `account_id -> value`.
+/// The index is written batch by batch into `{target_prefix}_tmp:{field}` keys, so it's never held
+/// in memory. Then the target keys that weren't rebuilt are deleted, and the temporary keys are
+/// moved over the target keys.
+async fn rebuild_index(
+    connection: &mut redis::aio::MultiplexedConnection,
+    source_prefix: &str,
+    target_prefix: &str,
+) -> Result<(), database::DatabaseError> {
+    let start = std::time::Instant::now();
+    let plan = &IndexRebuild::new(source_prefix, target_prefix);
+
+    // Clean up the temporary keys of an interrupted rebuild.
+    database::scan_all(
+        connection,
+        &format!("{}:*", plan.tmp_prefix),
+        (),
+        |mut c, (), keys| async move { database::delete_keys(&mut c, &keys).await },
+    )
+    .await?;
+
+    let entries = database::scan_all(
+        connection,
+        &format!("{}:*", source_prefix),
+        0,
+        |mut c, entries, keys| async move {
+            let values = database::query_hgetall_many(&mut c, &keys).await?;
+            let writes = plan.writes(&keys, values);
+            database::hset_many(&mut c, &writes).await?;
+            Ok(entries + writes.len())
+        },
+    )
+    .await?;
+
+    let removed = database::scan_all(
+        connection,
+        &format!("{}:*", target_prefix),
+        0,
+        |mut c, removed, keys| async move {
+            let tmp_keys = keys.iter().map(|key| plan.tmp_key(key)).collect::<Vec<_>>();
+            let tmp_exists = database::exists_many(&mut c, &tmp_keys).await?;
+            let stale = plan.stale_targets(keys, &tmp_exists);
+            database::delete_keys(&mut c, &stale).await?;
+            Ok(removed + stale.len())
+        },
+    )
+    .await?;
+
+    let rebuilt = database::scan_all(
+        connection,
+        &format!("{}:*", plan.tmp_prefix),
+        0,
+        |mut c, rebuilt, keys| async move {
+            let moves = plan.moves(keys);
+            database::move_keys(&mut c, &moves).await?;
+            Ok(rebuilt + moves.len())
+        },
+    )
+    .await?;
+
+    tracing::info!(target: TARGET_REVERSE_INDEX, "Rebuilt {} {} indexes ({} entries, {} removed) from {} in {}ms",
+        rebuilt,
+        target_prefix,
+        entries,
+        removed,
+        source_prefix,
+        start.elapsed().as_millis());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    #[test]
+    fn test_writes() {
+        let plan = IndexRebuild::new("pk", "ak");
+        let writes = plan.writes(
+            &strings(&["pk:ed25519:abc"]),
+            vec![vec![
+                ("alice.near".to_string(), "f".to_string()),
+                ("bob.near".to_string(), "l".to_string()),
+            ]],
+        );
+        assert_eq!(
+            writes,
+            vec![
+                (
+                    "ak_tmp:alice.near".to_string(),
+                    "ed25519:abc".to_string(),
+                    "f".to_string()
+                ),
+                (
+                    "ak_tmp:bob.near".to_string(),
+                    "ed25519:abc".to_string(),
+                    "l".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_account_with_all_keys_removed() {
+        // alice.near had a key that was removed from `pk:`, so there is no `ak_tmp:alice.near` and
+        // `ak:alice.near` must be deleted instead of being left with the removed key.
+        let plan = IndexRebuild::new("pk", "ak");
+        let targets = strings(&["ak:alice.near", "ak:bob.near"]);
+        let tmp_keys = targets
+            .iter()
+            .map(|key| plan.tmp_key(key))
+            .collect::<Vec<_>>();
+        assert_eq!(tmp_keys, strings(&["ak_tmp:alice.near", "ak_tmp:bob.near"]));
+        assert_eq!(
+            plan.stale_targets(targets, &[false, true]),
+            strings(&["ak:alice.near"])
+        );
+    }
+
+    #[test]
+    fn test_moves() {
+        let plan = IndexRebuild::new("st", "sd");
+        assert_eq!(
+            plan.moves(strings(&["sd_tmp:a.poolv1.near"])),
+            vec![(
+                "sd_tmp:a.poolv1.near".to_string(),
+                "sd:a.poolv1.near".to_string()
+            )]
+        );
+    }
+}
@@ -6,6 +6,7 @@ use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::time::Duration;
 
+const RPC_URL: &str = "https://beta.rpc.mainnet.near.org";
 const RPC_TIMEOUT: Duration = Duration::from_secs(10);
 const TARGET_RPC: &str = "rpc";
 
@@ -13,7 +14,18 @@ const TARGET_RPC: &str = "rpc";
 pub enum RpcError {
     ReqwestError(reqwest::Error),
     InvalidJsonRpcResponse,
-    InvalidFunctionCallResponse,
+    /// The JSON-RPC `error` object returned by the node.
+    NodeError(Value),
+}
+
+impl std::fmt::Display for RpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RpcError::ReqwestError(err) => write!(f, "ReqwestError({})", err),
+            RpcError::InvalidJsonRpcResponse => write!(f, "InvalidJsonRpcResponse"),
+            RpcError::NodeError(err) => write!(f, "NodeError({})", err),
+        }
+    }
 }
 
 impl From<reqwest::Error> for RpcError {
@@ -35,7 +47,7 @@ struct JsonResponse {
     id: String,
     // jsonrpc: String,
     result: Option<Value>,
-    // error: Option<Value>,
+    error: Option<Value>,
 }
 
 #[derive(Deserialize)]
@@ -45,60 +57,164 @@ struct FunctionCallResponse {
     result: Vec<u8>,
 }
 
-pub(crate) async fn get_ft_balances(
-    account_id: &str,
-    token_ids: &[(String, Option<BlockHeight>)],
-) -> Result<HashMap<String, Option<String>>, RpcError> {
-    let mut token_balances = HashMap::new();
-    if token_ids.is_empty() {
-        return Ok(token_balances);
+pub(crate) struct FunctionCall<'a> {
+    pub account_id: &'a str,
+    pub method_name: &'a str,
+    pub args: Value,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FtMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub icon: Option<String>,
+    pub reference: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct NftContractMetadata {
+    pub name: String,
+    pub symbol: String,
+    pub icon: Option<String>,
+    pub base_uri: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StakingBalance {
+    pub staked_balance: String,
+    pub unstaked_balance: String,
+    pub can_withdraw: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RewardFeeFraction {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StakingPoolInfo {
+    pub owner_id: String,
+    pub reward_fee_fraction: RewardFeeFraction,
+    pub total_staked_balance: String,
+    pub number_of_accounts: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LockupBalances {
+    pub owners_balance: String,
+    pub liquid_owners_balance: String,
+    pub locked_amount: String,
+}
+
+/// Calls view methods in a single batched JSON-RPC request.
+/// Returns the raw result bytes for every call in the same order, or `None` if the call failed.
+pub(crate) async fn call_functions(
+    calls: &[FunctionCall<'_>],
+) -> Result<Vec<Option<Vec<u8>>>, RpcError> {
+    if calls.is_empty() {
+        return Ok(vec![]);
     }
-    let start = std::time::Instant::now();
     let client = Client::new();
-    let request = token_ids
+    let request = calls
         .iter()
         .enumerate()
-        .map(|(id, (token_id, _))| JsonRequest {
+        .map(|(id, call)| JsonRequest {
             jsonrpc: "2.0".to_string(),
             method: "query".to_string(),
             params: json!({
                 "request_type": "call_function",
                 "finality": "final",
-                "account_id": token_id,
-                "method_name": "ft_balance_of",
-                "args_base64": BASE64_STANDARD.encode(format!("{{\"account_id\": \"{}\"}}", account_id)),
+                "account_id": call.account_id,
+                "method_name": call.method_name,
+                "args_base64": BASE64_STANDARD.encode(call.args.to_string()),
             }),
             id: id.to_string(),
         })
         .collect::<Vec<_>>();
     let response = client
-        .post("https://beta.rpc.mainnet.near.org")
+        .post(RPC_URL)
         .json(&request)
         .timeout(RPC_TIMEOUT)
         .send()
         .await?;
     let responses = response.json::<Vec<JsonResponse>>().await?;
+    parse_function_call_responses(responses, calls.len())
+}
+
+/// Matches the batched responses to the calls by id. A call that failed inside the contract is
+/// returned by the node as a result with an `error` field and no `result` bytes, so it's `None`
+/// like any other failed call, without failing the rest of the batch.
+fn parse_function_call_responses(
+    responses: Vec<JsonResponse>,
+    num_calls: usize,
+) -> Result<Vec<Option<Vec<u8>>>, RpcError> {
+    let mut results = vec![None; num_calls];
     for response in responses {
         let id: usize = response
             .id
             .parse()
             .map_err(|_| RpcError::InvalidJsonRpcResponse)?;
-        let token_id = token_ids
-            .get(id)
-            .ok_or(RpcError::InvalidJsonRpcResponse)?
-            .clone()
-            .0;
-        let balance = if let Some(res) = response.result {
-            let fc: FunctionCallResponse =
-                serde_json::from_value(res).map_err(|_| RpcError::InvalidFunctionCallResponse)?;
-            let balance: Option<String> = serde_json::from_slice(&fc.result).ok();
-            let parsed_balance: Option<u128> = balance.and_then(|s| s.parse().ok());
-            parsed_balance.map(|b| b.to_string())
-        } else {
-            None
-        };
-        token_balances.insert(token_id, balance);
+        let result = results
+            .get_mut(id)
+            .ok_or(RpcError::InvalidJsonRpcResponse)?;
+        *result = response
+            .result
+            .and_then(|res| serde_json::from_value::<FunctionCallResponse>(res).ok())
+            .map(|fc| fc.result);
+    }
+    Ok(results)
+}
+
+/// Calls a single JSON-RPC method and returns its result, or `None` if the account doesn't exist
+/// (`UNKNOWN_ACCOUNT`). Any other JSON-RPC error is returned as `RpcError::NodeError`.
+pub(crate) async fn call_method(method: &str, params: Value) -> Result<Option<Value>, RpcError> {
+    let client = Client::new();
+    let request = JsonRequest {
+        jsonrpc: "2.0".to_string(),
+        method: method.to_string(),
+        params,
+        id: "0".to_string(),
+    };
+    let response = client
+        .post(RPC_URL)
+        .json(&request)
+        .timeout(RPC_TIMEOUT)
+        .send()
+        .await?;
+    let response = response.json::<JsonResponse>().await?;
+    match (response.result, response.error) {
+        (Some(result), _) => Ok(Some(result)),
+        (None, Some(error)) if error["cause"]["name"] == "UNKNOWN_ACCOUNT" => Ok(None),
+        (None, Some(error)) => Err(RpcError::NodeError(error)),
+        (None, None) => Err(RpcError::InvalidJsonRpcResponse),
     }
+}
+
+pub(crate) async fn get_ft_balances(
+    account_id: &str,
+    token_ids: &[(String, Option<BlockHeight>)],
+) -> Result<HashMap<String, Option<String>>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = token_ids
+        .iter()
+        .map(|(token_id, _)| FunctionCall {
+            account_id: token_id,
+            method_name: "ft_balance_of",
+            args: json!({ "account_id": account_id }),
+        })
+        .collect::<Vec<_>>();
+    let results = call_functions(&calls).await?;
+    let token_balances = token_ids
+        .iter()
+        .zip(results)
+        .map(|((token_id, _), result)| {
+            let balance: Option<String> = result.and_then(|r| serde_json::from_slice(&r).ok());
+            let parsed_balance: Option<u128> = balance.and_then(|s| s.parse().ok());
+            (token_id.clone(), parsed_balance.map(|b| b.to_string()))
+        })
+        .collect();
     let duration = start.elapsed().as_millis();
 
     tracing::debug!(target: TARGET_RPC, "Query {}ms: get_ft_balances {} with {} tokens",
@@ -108,3 +224,490 @@ pub(crate) async fn get_ft_balances(
 
     Ok(token_balances)
 }
+
+pub(crate) async fn get_ft_metadata(
+    token_ids: &[String],
+) -> Result<Vec<Option<FtMetadata>>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = token_ids
+        .iter()
+        .map(|token_id| FunctionCall {
+            account_id: token_id,
+            method_name: "ft_metadata",
+            args: json!({}),
+        })
+        .collect::<Vec<_>>();
+    let results = call_functions(&calls).await?;
+    let metadata = results
+        .into_iter()
+        .map(|result| result.and_then(|r| serde_json::from_slice(&r).ok()))
+        .collect();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_ft_metadata for {} tokens",
+        duration,
+        token_ids.len());
+
+    Ok(metadata)
+}
+
+/// Returns the NFT tokens owned by the account on the given contract using `nft_tokens_for_owner`
+/// together with the total number of owned tokens from `nft_supply_for_owner`.
+pub(crate) async fn get_nft_tokens_for_owner(
+    contract_id: &str,
+    account_id: &str,
+    from_index: u64,
+    limit: u64,
+) -> Result<(Option<Vec<Value>>, Option<u64>), RpcError> {
+    let start = std::time::Instant::now();
+    let calls = [
+        FunctionCall {
+            account_id: contract_id,
+            method_name: "nft_tokens_for_owner",
+            args: json!({
+                "account_id": account_id,
+                "from_index": from_index.to_string(),
+                "limit": limit,
+            }),
+        },
+        FunctionCall {
+            account_id: contract_id,
+            method_name: "nft_supply_for_owner",
+            args: json!({ "account_id": account_id }),
+        },
+    ];
+    let (tokens, supply) = parse_nft_tokens_for_owner(call_functions(&calls).await?);
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_nft_tokens_for_owner {} on {}",
+        duration,
+        account_id,
+        contract_id);
+
+    Ok((tokens, supply))
+}
+
+/// Parses the results of `nft_tokens_for_owner` and `nft_supply_for_owner`. Either is `None` if
+/// the contract doesn't implement the method or it failed.
+fn parse_nft_tokens_for_owner(results: Vec<Option<Vec<u8>>>) -> (Option<Vec<Value>>, Option<u64>) {
+    let mut results = results.into_iter();
+    let tokens: Option<Vec<Value>> = results
+        .next()
+        .flatten()
+        .and_then(|r| serde_json::from_slice(&r).ok());
+    let supply: Option<u64> = results
+        .next()
+        .flatten()
+        .and_then(|r| serde_json::from_slice::<String>(&r).ok())
+        .and_then(|s| s.parse().ok());
+    (tokens, supply)
+}
+
+pub(crate) async fn get_nft_metadata(
+    contract_ids: &[String],
+) -> Result<Vec<Option<NftContractMetadata>>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = contract_ids
+        .iter()
+        .map(|contract_id| FunctionCall {
+            account_id: contract_id,
+            method_name: "nft_metadata",
+            args: json!({}),
+        })
+        .collect::<Vec<_>>();
+    let results = call_functions(&calls).await?;
+    let metadata = results
+        .into_iter()
+        .map(|result| result.and_then(|r| serde_json::from_slice(&r).ok()))
+        .collect();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_nft_metadata for {} contracts",
+        duration,
+        contract_ids.len());
+
+    Ok(metadata)
+}
+
+pub(crate) async fn get_nft_supply_for_owner(
+    account_id: &str,
+    contract_ids: &[String],
+) -> Result<Vec<Option<u64>>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = contract_ids
+        .iter()
+        .map(|contract_id| FunctionCall {
+            account_id: contract_id,
+            method_name: "nft_supply_for_owner",
+            args: json!({ "account_id": account_id }),
+        })
+        .collect::<Vec<_>>();
+    let results = call_functions(&calls).await?;
+    let supplies = results
+        .into_iter()
+        .map(|result| {
+            result
+                .and_then(|r| serde_json::from_slice::<String>(&r).ok())
+                .and_then(|s| s.parse().ok())
+        })
+        .collect();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_nft_supply_for_owner {} on {} contracts",
+        duration,
+        account_id,
+        contract_ids.len());
+
+    Ok(supplies)
+}
+
+pub(crate) async fn get_epoch_id() -> Result<String, RpcError> {
+    let start = std::time::Instant::now();
+    let block = call_method("block", json!({ "finality": "final" }))
+        .await?
+        .ok_or(RpcError::InvalidJsonRpcResponse)?;
+    let epoch_id = block["header"]["epoch_id"]
+        .as_str()
+        .ok_or(RpcError::InvalidJsonRpcResponse)?
+        .to_string();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_epoch_id", duration);
+
+    Ok(epoch_id)
+}
+
+/// Returns the staked and unstaked balances of the account on each of the given staking pools.
+pub(crate) async fn get_staking_balances(
+    account_id: &str,
+    pool_ids: &[String],
+) -> Result<Vec<Option<StakingBalance>>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = pool_ids
+        .iter()
+        .flat_map(|pool_id| {
+            [
+                "get_account_staked_balance",
+                "get_account_unstaked_balance",
+                "is_account_unstaked_balance_available",
+            ]
+            .map(|method_name| FunctionCall {
+                account_id: pool_id,
+                method_name,
+                args: json!({ "account_id": account_id }),
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = call_functions(&calls).await?;
+    let balances = results
+        .chunks(3)
+        .map(|chunk| {
+            let parse_balance = |r: &Option<Vec<u8>>| {
+                r.as_ref()
+                    .and_then(|r| serde_json::from_slice::<String>(r).ok())
+                    .and_then(|s| s.parse::<u128>().ok())
+                    .map(|b| b.to_string())
+            };
+            Some(StakingBalance {
+                staked_balance: parse_balance(&chunk[0])?,
+                unstaked_balance: parse_balance(&chunk[1])?,
+                can_withdraw: chunk[2]
+                    .as_ref()
+                    .and_then(|r| serde_json::from_slice(r).ok())?,
+            })
+        })
+        .collect();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_staking_balances {} on {} pools",
+        duration,
+        account_id,
+        pool_ids.len());
+
+    Ok(balances)
+}
+
+pub(crate) async fn get_staking_pool_info(
+    pool_id: &str,
+) -> Result<Option<StakingPoolInfo>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = [
+        "get_owner_id",
+        "get_reward_fee_fraction",
+        "get_total_staked_balance",
+        "get_number_of_accounts",
+    ]
+    .map(|method_name| FunctionCall {
+        account_id: pool_id,
+        method_name,
+        args: json!({}),
+    });
+    let results = call_functions(&calls).await?;
+    let info = (|| {
+        Some(StakingPoolInfo {
+            owner_id: serde_json::from_slice(results[0].as_ref()?).ok()?,
+            reward_fee_fraction: serde_json::from_slice(results[1].as_ref()?).ok()?,
+            total_staked_balance: serde_json::from_slice::<String>(results[2].as_ref()?)
+                .ok()?
+                .parse::<u128>()
+                .ok()?
+                .to_string(),
+            number_of_accounts: serde_json::from_slice(results[3].as_ref()?).ok()?,
+        })
+    })();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_staking_pool_info {}",
+        duration,
+        pool_id);
+
+    Ok(info)
+}
+
+/// Returns the account IDs of the validators in the current epoch.
+pub(crate) async fn get_current_validators() -> Result<Vec<String>, RpcError> {
+    let start = std::time::Instant::now();
+    let validators = call_method("validators", json!([null]))
+        .await?
+        .ok_or(RpcError::InvalidJsonRpcResponse)?;
+    let account_ids = validators["current_validators"]
+        .as_array()
+        .ok_or(RpcError::InvalidJsonRpcResponse)?
+        .iter()
+        .filter_map(|v| v["account_id"].as_str().map(|s| s.to_string()))
+        .collect::<Vec<_>>();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_current_validators {} validators",
+        duration,
+        account_ids.len());
+
+    Ok(account_ids)
+}
+
+/// Returns the access keys of the account using `view_access_key_list`, or `None` if the account
+/// doesn't exist.
+pub(crate) async fn get_access_keys(account_id: &str) -> Result<Option<Vec<Value>>, RpcError> {
+    let start = std::time::Instant::now();
+    let result = call_method(
+        "query",
+        json!({
+            "request_type": "view_access_key_list",
+            "finality": "final",
+            "account_id": account_id,
+        }),
+    )
+    .await?;
+    let keys = result
+        .map(|result| {
+            serde_json::from_value::<Vec<Value>>(result["keys"].clone())
+                .map_err(|_| RpcError::InvalidJsonRpcResponse)
+        })
+        .transpose()?;
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_access_keys {}",
+        duration,
+        account_id);
+
+    Ok(keys)
+}
+
+pub(crate) async fn get_lockup_balances(
+    lockup_id: &str,
+) -> Result<Option<LockupBalances>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = [
+        "get_owners_balance",
+        "get_liquid_owners_balance",
+        "get_locked_amount",
+    ]
+    .map(|method_name| FunctionCall {
+        account_id: lockup_id,
+        method_name,
+        args: json!({}),
+    });
+    let results = call_functions(&calls).await?;
+    let parse_balance = |r: &Option<Vec<u8>>| {
+        r.as_ref()
+            .and_then(|r| serde_json::from_slice::<String>(r).ok())
+            .and_then(|s| s.parse::<u128>().ok())
+            .map(|b| b.to_string())
+    };
+    let balances = (|| {
+        Some(LockupBalances {
+            owners_balance: parse_balance(&results[0])?,
+            liquid_owners_balance: parse_balance(&results[1])?,
+            locked_amount: parse_balance(&results[2])?,
+        })
+    })();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_lockup_balances {}",
+        duration,
+        lockup_id);
+
+    Ok(balances)
+}
+
+/// Returns the `ft_balance_of` of every account on the token, or `None` if the call failed.
+pub(crate) async fn get_ft_balances_of_accounts(
+    token_id: &str,
+    account_ids: &[String],
+) -> Result<Vec<Option<String>>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = account_ids
+        .iter()
+        .map(|account_id| FunctionCall {
+            account_id: token_id,
+            method_name: "ft_balance_of",
+            args: json!({ "account_id": account_id }),
+        })
+        .collect::<Vec<_>>();
+    let results = call_functions(&calls).await?;
+    let balances = results
+        .into_iter()
+        .map(|result| {
+            let balance: Option<String> = result.and_then(|r| serde_json::from_slice(&r).ok());
+            let parsed_balance: Option<u128> = balance.and_then(|s| s.parse().ok());
+            parsed_balance.map(|b| b.to_string())
+        })
+        .collect();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_ft_balances_of_accounts on {} for {} accounts",
+        duration,
+        token_id,
+        account_ids.len());
+
+    Ok(balances)
+}
+
+/// Returns the storage balances of the accounts on the token contract using `storage_balance_of`.
+/// The inner `None` means the account is not registered, while the outer `None` means the call
+/// failed.
+pub(crate) async fn get_storage_balances(
+    token_id: &str,
+    account_ids: &[String],
+) -> Result<Vec<Option<Option<Value>>>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = account_ids
+        .iter()
+        .map(|account_id| FunctionCall {
+            account_id: token_id,
+            method_name: "storage_balance_of",
+            args: json!({ "account_id": account_id }),
+        })
+        .collect::<Vec<_>>();
+    let results = call_functions(&calls).await?;
+    let storage_balances = results
+        .into_iter()
+        .map(|result| result.and_then(|r| serde_json::from_slice(&r).ok()))
+        .collect();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_storage_balances on {} for {} accounts",
+        duration,
+        token_id,
+        account_ids.len());
+
+    Ok(storage_balances)
+}
+
+pub(crate) async fn get_storage_balance_bounds(
+    token_ids: &[String],
+) -> Result<Vec<Option<Value>>, RpcError> {
+    let start = std::time::Instant::now();
+    let calls = token_ids
+        .iter()
+        .map(|token_id| FunctionCall {
+            account_id: token_id,
+            method_name: "storage_balance_bounds",
+            args: json!({}),
+        })
+        .collect::<Vec<_>>();
+    let results = call_functions(&calls).await?;
+    let bounds = results
+        .into_iter()
+        .map(|result| result.and_then(|r| serde_json::from_slice(&r).ok()))
+        .collect();
+    let duration = start.elapsed().as_millis();
+
+    tracing::debug!(target: TARGET_RPC, "Query {}ms: get_storage_balance_bounds for {} tokens",
+        duration,
+        token_ids.len());
+
+    Ok(bounds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_function_call_responses() {
+        let responses: Vec<JsonResponse> = serde_json::from_value(json!([
+            {
+                "jsonrpc": "2.0",
+                "id": "1",
+                "result": {
+                    "block_hash": "11111111111111111111111111111111",
+                    "block_height": 100,
+                    "error": "wasm execution failed with error: MethodResolveError(MethodNotFound)",
+                    "logs": [],
+                },
+            },
+            {
+                "jsonrpc": "2.0",
+                "id": "0",
+                "result": {
+                    "block_hash": "11111111111111111111111111111111",
+                    "block_height": 100,
+                    "logs": [],
+                    "result": [34, 49, 48, 34],
+                },
+            },
+            {
+                "jsonrpc": "2.0",
+                "id": "2",
+                "error": {"name": "HANDLER_ERROR", "cause": {"name": "UNKNOWN_ACCOUNT"}},
+            },
+        ]))
+        .unwrap();
+        let results = parse_function_call_responses(responses, 4).unwrap();
+        assert_eq!(results, vec![Some(b"\"10\"".to_vec()), None, None, None]);
+    }
+
+    #[test]
+    fn test_nft_contract_without_owner_methods() {
+        let method_not_found = json!({
+            "block_hash": "11111111111111111111111111111111",
+            "block_height": 100,
+            "error": "wasm execution failed with error: MethodResolveError(MethodNotFound)",
+            "logs": [],
+        });
+        let responses: Vec<JsonResponse> = serde_json::from_value(json!([
+            {"jsonrpc": "2.0", "id": "0", "result": method_not_found},
+            {"jsonrpc": "2.0", "id": "1", "result": method_not_found},
+        ]))
+        .unwrap();
+        let results = parse_function_call_responses(responses, 2).unwrap();
+        let (tokens, supply) = parse_nft_tokens_for_owner(results);
+        assert!(tokens.is_none());
+        assert!(supply.is_none());
+
+        let (tokens, supply) = parse_nft_tokens_for_owner(vec![
+            Some(br#"[{"token_id": "1", "owner_id": "alice.near"}]"#.to_vec()),
+            Some(br#""3""#.to_vec()),
+        ]);
+        assert_eq!(tokens.unwrap()[0]["token_id"], "1");
+        assert_eq!(supply, Some(3));
+    }
+
+    #[test]
+    fn test_parse_function_call_responses_invalid_id() {
+        let responses: Vec<JsonResponse> =
+            serde_json::from_value(json!([{"jsonrpc": "2.0", "id": "1", "result": null}])).unwrap();
+        assert!(parse_function_call_responses(responses, 1).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use serde_json::json;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// Coalesces identical concurrent queries, so only the first caller for a key runs the query and
+/// the callers that arrive while it's in flight receive a clone of its result.
+/// If the query fails or the first caller is cancelled, the waiting callers run the query
+/// themselves, so errors don't need to be cloned.
+pub(crate) struct SingleFlight<T> {
+    in_flight: Mutex<HashMap<String, broadcast::Sender<T>>>,
+    /// The number of queries that were executed.
+    queries: AtomicU64,
+    /// The number of callers that received the result of another caller's query.
+    coalesced: AtomicU64,
+}
+
+/// Removes the in-flight entry if the query future is dropped before it completes.
+struct InFlightGuard<'a, T> {
+    single_flight: &'a SingleFlight<T>,
+    key: Option<String>,
+}
+
+impl<T> InFlightGuard<'_, T> {
+    fn finish(mut self) -> Option<broadcast::Sender<T>> {
+        let key = self.key.take()?;
+        self.single_flight.in_flight.lock().unwrap().remove(&key)
+    }
+}
+
+impl<T> Drop for InFlightGuard<'_, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.single_flight.in_flight.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            in_flight: Mutex::new(HashMap::new()),
+            queries: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    /// Runs the query for the key, unless an identical query is already in flight, in which case
+    /// waits for its result.
+    pub(crate) async fn run<E, F, Fut>(&self, key: String, query: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let receiver = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key) {
+                Some(sender) => Some(sender.subscribe()),
+                None => {
+                    in_flight.insert(key.clone(), broadcast::channel(1).0);
+                    None
+                }
+            }
+        };
+
+        if let Some(mut receiver) = receiver {
+            if let Ok(value) = receiver.recv().await {
+                self.coalesced.fetch_add(1, Ordering::Relaxed);
+                return Ok(value);
+            }
+            self.queries.fetch_add(1, Ordering::Relaxed);
+            return query().await;
+        }
+
+        let guard = InFlightGuard {
+            single_flight: self,
+            key: Some(key),
+        };
+        self.queries.fetch_add(1, Ordering::Relaxed);
+        let res = query().await;
+        if let (Some(sender), Ok(value)) = (guard.finish(), res.as_ref()) {
+            // No receivers is not an error, the query just wasn't coalesced.
+            let _ = sender.send(value.clone());
+        }
+        res
+    }
+
+    pub(crate) fn metrics(&self) -> serde_json::Value {
+        json!({
+            "queries": self.queries.load(Ordering::Relaxed),
+            "coalesced": self.coalesced.load(Ordering::Relaxed),
+            "in_flight": self.in_flight.lock().unwrap().len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::oneshot;
+
+    fn assert_metrics(single_flight: &SingleFlight<u64>, queries: u64, coalesced: u64) {
+        assert_eq!(
+            single_flight.metrics(),
+            json!({
+                "queries": queries,
+                "coalesced": coalesced,
+                "in_flight": 0,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_coalesced_result() {
+        let single_flight = SingleFlight::<u64>::new();
+        let (sender, receiver) = oneshot::channel::<()>();
+
+        let (leader, follower, _) = tokio::join!(
+            single_flight.run("key".to_string(), || async {
+                receiver.await.unwrap();
+                Ok::<_, ()>(1)
+            }),
+            single_flight.run("key".to_string(), || async { Ok::<_, ()>(2) }),
+            async {
+                assert_eq!(single_flight.metrics()["in_flight"], 1);
+                sender.send(()).unwrap();
+            },
+        );
+
+        assert_eq!(leader, Ok(1));
+        assert_eq!(follower, Ok(1));
+        assert_metrics(&single_flight, 1, 1);
+    }
+
+    #[tokio::test]
+    async fn test_leader_error() {
+        let single_flight = SingleFlight::<u64>::new();
+        let (sender, receiver) = oneshot::channel::<()>();
+
+        let (leader, follower, _) = tokio::join!(
+            single_flight.run("key".to_string(), || async {
+                receiver.await.unwrap();
+                Err("failed")
+            }),
+            single_flight.run("key".to_string(), || async { Ok::<_, &str>(2) }),
+            async { sender.send(()).unwrap() },
+        );
+
+        assert_eq!(leader, Err("failed"));
+        // The error isn't shared, so the follower runs the query itself.
+        assert_eq!(follower, Ok(2));
+        assert_metrics(&single_flight, 2, 0);
+    }
+
+    #[tokio::test]
+    async fn test_leader_cancelled() {
+        let single_flight = SingleFlight::<u64>::new();
+
+        let (leader, follower) = tokio::join!(
+            tokio::time::timeout(
+                Duration::from_millis(10),
+                single_flight.run("key".to_string(), std::future::pending::<Result<u64, ()>>),
+            ),
+            single_flight.run("key".to_string(), || async { Ok::<_, ()>(3) }),
+        );
+
+        assert!(leader.is_err());
+        // Dropping the leader removes the in-flight entry, so the follower runs the query itself.
+        assert_eq!(follower, Ok(3));
+        assert_metrics(&single_flight, 2, 0);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::api::BlockHeight;
+use crate::rpc::{self, StakingBalance, StakingPoolInfo};
+use crate::{cache, database};
+
+/// The current epoch ID is re-checked every minute.
+const EPOCH_ID_TTL_SEC: u64 = 60;
+/// Values cached per epoch are keyed by the epoch ID, so the expiry only needs to outlive an epoch.
+const EPOCH_TTL_SEC: u64 = 13 * 60 * 60;
+/// How long to wait before retrying a pool that didn't return valid data.
+const MISSING_TTL_SEC: u64 = 5 * 60;
+
+/// Returns the current epoch ID from the `epoch_id` cache, or `None` if it's not available.
+pub(crate) async fn get_epoch_id(
+    connection: &mut redis::aio::MultiplexedConnection,
+) -> Result<Option<String>, database::DatabaseError> {
+    let mut res = cache::get_or_fetch(
+        connection,
+        &["epoch_id".to_string()],
+        EPOCH_ID_TTL_SEC,
+        EPOCH_ID_TTL_SEC,
+        |_| async {
+            rpc::get_epoch_id()
+                .await
+                .map(|epoch_id| vec![Some(epoch_id)])
+        },
+    )
+    .await?;
+    Ok(res.pop().flatten())
+}
+
+/// Returns the staked and unstaked balances of the account on each of the given pools. Balances
+/// are cached in `stb:{pool_id}:{account_id}:{epoch_id}:{last_update_block_height}`, so they are
+/// refreshed on every new epoch (rewards) and on every staking action of the account.
+/// The last known staked balances are also recorded in the `sdb:{pool_id}` hashes to order the
+/// delegators of a pool.
+pub(crate) async fn get_staking_balances(
+    connection: &mut redis::aio::MultiplexedConnection,
+    account_id: &str,
+    pools: &[(String, Option<BlockHeight>)],
+) -> Result<Vec<Option<StakingBalance>>, database::DatabaseError> {
+    if pools.is_empty() {
+        return Ok(vec![]);
+    }
+    let Some(epoch_id) = get_epoch_id(connection).await? else {
+        return Ok(pools.iter().map(|_| None).collect());
+    };
+    let keys = pools
+        .iter()
+        .map(|(pool_id, last_update_block_height)| {
+            format!(
+                "stb:{}:{}:{}:{}",
+                pool_id,
+                account_id,
+                epoch_id,
+                last_update_block_height
+                    .map(|h| h.to_string())
+                    .unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    let balances = cache::get_or_fetch(
+        connection,
+        &keys,
+        EPOCH_TTL_SEC,
+        MISSING_TTL_SEC,
+        |missing| {
+            let pool_ids = missing
+                .into_iter()
+                .map(|i| pools[i].0.clone())
+                .collect::<Vec<_>>();
+            async move { rpc::get_staking_balances(account_id, &pool_ids).await }
+        },
+    )
+    .await?;
+
+    let known_balances = pools
+        .iter()
+        .zip(balances.iter())
+        .filter_map(|((pool_id, _), balance)| {
+            balance.as_ref().map(|balance| {
+                (
+                    format!("sdb:{}", pool_id),
+                    account_id.to_string(),
+                    balance.staked_balance.clone(),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    database::hset_many(connection, &known_balances).await?;
+
+    Ok(balances)
+}
+
+/// Returns the staking pool info and whether the pool is in the current validator set. Both are
+/// cached per epoch in `pool:{pool_id}:{epoch_id}` and `validators:{epoch_id}`.
+pub(crate) async fn get_staking_pool_info(
+    connection: &mut redis::aio::MultiplexedConnection,
+    pool_id: &str,
+) -> Result<(Option<StakingPoolInfo>, Option<bool>), database::DatabaseError> {
+    let Some(epoch_id) = get_epoch_id(connection).await? else {
+        return Ok((None, None));
+    };
+    let info = cache::get_or_fetch(
+        connection,
+        &[format!("pool:{}:{}", pool_id, epoch_id)],
+        EPOCH_TTL_SEC,
+        MISSING_TTL_SEC,
+        |_| async {
+            rpc::get_staking_pool_info(pool_id)
+                .await
+                .map(|info| vec![info])
+        },
+    )
+    .await?
+    .pop()
+    .flatten();
+    let validators = cache::get_or_fetch(
+        connection,
+        &[format!("validators:{}", epoch_id)],
+        EPOCH_TTL_SEC,
+        MISSING_TTL_SEC,
+        |_| async {
+            rpc::get_current_validators()
+                .await
+                .map(|validators| vec![Some(validators)])
+        },
+    )
+    .await?
+    .pop()
+    .flatten();
+    let is_validator = validators.map(|validators| validators.iter().any(|v| v == pool_id));
+    Ok((info, is_validator))
+}
@@ -1,46 +1,303 @@
 use crate::api::HealthError;
+use crate::status_history::{StatusHistory, StatusSample};
 use crate::*;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::middleware::Next;
 use actix_web::{get, web, Responder};
+use serde::Deserialize;
 use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
 
-async fn internal_status(
-    app_state: &web::Data<AppState>,
-) -> Result<serde_json::Value, api::ServiceError> {
-    let mut connection = app_state
-        .redis_client
-        .get_multiplexed_async_connection()
-        .await?;
-
-    let latest_sync_block = database::query_get(&mut connection, "meta:latest_block").await?;
-    let latest_block_time = database::query_get(&mut connection, "meta:latest_block_time").await?;
-    let latest_balance_block =
-        database::query_get(&mut connection, "meta:latest_balance_block").await?;
-
-    let sync_latency_sec = latest_block_time.as_ref().map(|t| {
-        let t_nano = t.parse::<u128>().unwrap_or(0);
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default();
-        now.as_nanos().saturating_sub(t_nano) as f64 / 1e9
-    });
-
-    Ok(json!({
+const TARGET_STATUS: &str = "status";
+
+/// The indexer subsystems whose progress is reported by default. The progress of a subsystem is
+/// read from `meta:latest_{subsystem}_block` and `meta:latest_{subsystem}_block_time`.
+pub const DEFAULT_SYNC_SUBSYSTEMS: &str = "public_keys,ft,nft,staking,accounts,balance";
+
+/// The health thresholds of an indexer subsystem.
+#[derive(Clone, Deserialize)]
+pub struct SubsystemThreshold {
+    /// The maximum number of blocks the subsystem can be behind the latest synced block.
+    pub max_lag_blocks: Option<u64>,
+    /// The maximum time since the latest block processed by the subsystem.
+    pub max_latency_sec: Option<f64>,
+}
+
+/// The sync latency and balance block difference thresholds of a named health profile.
+#[derive(Clone, Deserialize)]
+pub struct HealthProfile {
+    pub max_latency_sec: Option<f64>,
+    pub max_block_diff: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct HealthQuery {
+    /// The name of a configured health profile.
+    pub profile: Option<String>,
+    /// Overrides the maximum sync latency, clamped by the configured bounds.
+    pub max_latency_sec: Option<f64>,
+    /// Overrides the maximum balance block difference, clamped by the configured bound.
+    pub max_block_diff: Option<u64>,
+}
+
+/// The thresholds the health check is evaluated with.
+pub struct HealthThresholds {
+    pub max_latency_sec: f64,
+    pub max_block_diff: u64,
+}
+
+impl HealthThresholds {
+    /// The server default thresholds.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_latency_sec: config.max_healthy_latency_sec,
+            max_block_diff: config.max_healthy_sync_block_diff,
+        }
+    }
+
+    /// Resolves the thresholds from the query parameters, then the profile, then the global
+    /// config. Returns `None` if the profile doesn't exist or the latency is not a number.
+    fn resolve(query: &HealthQuery, config: &Config) -> Option<Self> {
+        if query.max_latency_sec.is_some_and(|l| !l.is_finite()) {
+            return None;
+        }
+        let defaults = Self::from_config(config);
+        let profile = match &query.profile {
+            Some(name) => Some(config.health_profiles.get(name)?),
+            None => None,
+        };
+        let max_latency_sec = match query.max_latency_sec {
+            Some(max_latency_sec) => max_latency_sec.clamp(
+                config.min_health_latency_bound_sec,
+                config.max_health_latency_bound_sec,
+            ),
+            None => profile
+                .and_then(|p| p.max_latency_sec)
+                .unwrap_or(defaults.max_latency_sec),
+        };
+        let max_block_diff = match query.max_block_diff {
+            Some(max_block_diff) => max_block_diff.min(config.max_health_block_diff_bound),
+            None => profile
+                .and_then(|p| p.max_block_diff)
+                .unwrap_or(defaults.max_block_diff),
+        };
+        Some(Self {
+            max_latency_sec,
+            max_block_diff,
+        })
+    }
+}
+
+/// The latest block processed by an indexer subsystem.
+#[derive(Clone)]
+pub struct SubsystemStatus {
+    pub block_height: u64,
+    pub block_time: Option<String>,
+}
+
+/// The latest values of the sync `meta:*` keys, published by the status poller.
+#[derive(Clone, Default)]
+pub struct SyncStatus {
+    pub latest_sync_block: Option<String>,
+    pub latest_block_time: Option<String>,
+    pub latest_balance_block: Option<String>,
+    /// The subsystems that reported their progress, by name.
+    pub subsystems: BTreeMap<String, SubsystemStatus>,
+}
+
+/// Returns the time in seconds since the given timestamp in nanoseconds.
+fn latency_sec(timestamp_nanosec: &str) -> f64 {
+    let t_nano = timestamp_nanosec.parse::<u128>().unwrap_or(0);
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    now.as_nanos().saturating_sub(t_nano) as f64 / 1e9
+}
+
+impl SyncStatus {
+    pub fn sync_block_height(&self) -> Option<u64> {
+        self.latest_sync_block
+            .as_ref()
+            .map(|s| s.parse::<u64>().unwrap_or(0))
+    }
+
+    /// The time since the latest synced block, computed at the time of the call.
+    pub fn sync_latency_sec(&self) -> Option<f64> {
+        self.latest_block_time.as_deref().map(latency_sec)
+    }
+
+    /// Returns the sample of the sync status at the current time for the status history.
+    pub fn sample(&self) -> StatusSample {
+        let sync_block_height = self.sync_block_height();
+        let balance_block_height = self
+            .latest_balance_block
+            .as_ref()
+            .and_then(|s| s.parse::<u64>().ok());
+        StatusSample {
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            sync_block_height,
+            sync_latency_sec: self.sync_latency_sec(),
+            balance_block_diff: sync_block_height
+                .zip(balance_block_height)
+                .map(|(sync, balance)| sync.saturating_sub(balance)),
+        }
+    }
+
+    /// The progress of every subsystem with its lag behind the latest synced block.
+    pub fn subsystems_status(&self) -> serde_json::Value {
+        let sync_block_height = self.sync_block_height();
+        self.subsystems
+            .iter()
+            .map(|(name, subsystem)| {
+                (
+                    name.clone(),
+                    json!({
+                        "block_height": subsystem.block_height,
+                        "lag_blocks": sync_block_height
+                            .map(|height| height.saturating_sub(subsystem.block_height)),
+                        "latency_sec": subsystem.block_time.as_deref().map(latency_sec),
+                    }),
+                )
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+}
+
+/// Periodically reads the sync `meta:*` keys and publishes them to the channel, so `/status`,
+/// `/health` and the freshness headers are served from memory. Every successful poll is also
+/// recorded in the status history.
+pub async fn run(
+    redis_client: redis::Client,
+    interval: std::time::Duration,
+    subsystems: Vec<String>,
+    sender: tokio::sync::watch::Sender<SyncStatus>,
+    history: Arc<Mutex<StatusHistory>>,
+) {
+    // The connection is reused between polls and only reopened after an error.
+    let mut connection: Option<redis::aio::MultiplexedConnection> = None;
+    loop {
+        let res = match connection.as_mut() {
+            Some(connection) => poll(connection, &subsystems).await,
+            None => match redis_client.get_multiplexed_async_connection().await {
+                Ok(new_connection) => poll(connection.insert(new_connection), &subsystems).await,
+                Err(err) => Err(err.into()),
+            },
+        };
+        if res.is_err() {
+            connection = None;
+        }
+        match res {
+            Ok(sync_status) => {
+                history.lock().unwrap().record(sync_status.sample());
+                sender.send_replace(sync_status);
+            }
+            Err(err) => {
+                tracing::error!(target: TARGET_STATUS, "Failed to poll sync status: {:?}", err);
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn poll(
+    connection: &mut redis::aio::MultiplexedConnection,
+    subsystems: &[String],
+) -> Result<SyncStatus, database::DatabaseError> {
+    let mut keys = vec![
+        "meta:latest_block".to_string(),
+        "meta:latest_block_time".to_string(),
+        "meta:latest_balance_block".to_string(),
+    ];
+    for subsystem in subsystems {
+        keys.push(format!("meta:latest_{}_block", subsystem));
+        keys.push(format!("meta:latest_{}_block_time", subsystem));
+    }
+    let mut values = database::query_mget(connection, &keys).await?.into_iter();
+
+    Ok(SyncStatus {
+        latest_sync_block: values.next().flatten(),
+        latest_block_time: values.next().flatten(),
+        latest_balance_block: values.next().flatten(),
+        subsystems: subsystems
+            .iter()
+            .filter_map(|subsystem| {
+                let block_height = values.next().flatten();
+                let block_time = values.next().flatten();
+                Some((
+                    subsystem.clone(),
+                    SubsystemStatus {
+                        block_height: block_height?.parse().ok()?,
+                        block_time,
+                    },
+                ))
+            })
+            .collect(),
+    })
+}
+
+/// Adds the `X-Sync-Block-Height` and `X-Sync-Latency-Sec` headers to every response, so clients
+/// can tell how fresh the indexed data is.
+pub async fn freshness_headers(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let sync_status = request
+        .app_data::<web::Data<AppState>>()
+        .map(|app_state| app_state.sync_status.borrow().clone());
+    let mut response = next.call(request).await?;
+    if let Some(sync_status) = sync_status {
+        let headers = response.headers_mut();
+        if let Some(height) = sync_status.sync_block_height() {
+            headers.insert(
+                HeaderName::from_static("x-sync-block-height"),
+                HeaderValue::from(height),
+            );
+        }
+        if let Some(latency) = sync_status
+            .sync_latency_sec()
+            .and_then(|latency| HeaderValue::from_str(&format!("{:.3}", latency)).ok())
+        {
+            headers.insert(HeaderName::from_static("x-sync-latency-sec"), latency);
+        }
+    }
+    Ok(response)
+}
+
+fn internal_status(app_state: &web::Data<AppState>) -> serde_json::Value {
+    status_json(app_state.sync_status.borrow().clone())
+}
+
+pub(crate) fn status_json(sync_status: SyncStatus) -> serde_json::Value {
+    json!({
         "version": env!("CARGO_PKG_VERSION"),
-        "sync_block_height": latest_sync_block.map(|s| s.parse::<u64>().unwrap_or(0)),
-        "sync_latency_sec": sync_latency_sec,
-        "sync_block_timestamp_nanosec": latest_block_time,
-        "sync_balance_block_height": latest_balance_block.map(|s| s.parse::<u64>().unwrap_or(0)),
-    }))
+        "subsystems": sync_status.subsystems_status(),
+        "sync_block_height": sync_status.sync_block_height(),
+        "sync_latency_sec": sync_status.sync_latency_sec(),
+        "sync_block_timestamp_nanosec": sync_status.latest_block_time,
+        "sync_balance_block_height": sync_status.latest_balance_block.map(|s| s.parse::<u64>().unwrap_or(0)),
+        "single_flight": database::single_flight_metrics(),
+    })
 }
 
-fn is_healthy(v: serde_json::Value, config: &Config) -> Result<(), HealthError> {
+pub(crate) fn is_healthy(
+    v: serde_json::Value,
+    config: &Config,
+    thresholds: &HealthThresholds,
+) -> Result<(), HealthError> {
     let latency = v["sync_latency_sec"]
         .as_f64()
         .ok_or(HealthError::MissingSyncLatency)?;
-    if latency > config.max_healthy_latency_sec {
+    if latency > thresholds.max_latency_sec {
         return Err(HealthError::HighSyncLatency {
             latency,
-            max_latency: config.max_healthy_latency_sec,
+            max_latency: thresholds.max_latency_sec,
         });
     }
     let latest_sync_block = v["sync_block_height"]
@@ -50,12 +307,46 @@ fn is_healthy(v: serde_json::Value, config: &Config) -> Result<(), HealthError>
         .as_u64()
         .ok_or(HealthError::MissingSyncBalanceBlockHeight)?;
     let sync_difference = latest_sync_block.saturating_sub(latest_balance_block);
-    if sync_difference > config.max_healthy_sync_block_diff {
+    if sync_difference > thresholds.max_block_diff {
         return Err(HealthError::HighSyncBlockDiff {
             sync_difference,
-            max_sync_difference: config.max_healthy_sync_block_diff,
+            max_sync_difference: thresholds.max_block_diff,
         });
     }
+    for (subsystem, threshold) in &config.subsystem_thresholds {
+        let subsystem_status = &v["subsystems"][subsystem];
+        if subsystem_status.is_null() {
+            return Err(HealthError::MissingSubsystem {
+                subsystem: subsystem.clone(),
+            });
+        }
+        if let (Some(lag_blocks), Some(max_lag_blocks)) = (
+            subsystem_status["lag_blocks"].as_u64(),
+            threshold.max_lag_blocks,
+        ) {
+            if lag_blocks > max_lag_blocks {
+                return Err(HealthError::HighSubsystemLag {
+                    subsystem: subsystem.clone(),
+                    lag_blocks,
+                    max_lag_blocks,
+                });
+            }
+        }
+        if let Some(max_latency) = threshold.max_latency_sec {
+            let latency = subsystem_status["latency_sec"].as_f64().ok_or_else(|| {
+                HealthError::MissingSubsystemLatency {
+                    subsystem: subsystem.clone(),
+                }
+            })?;
+            if latency > max_latency {
+                return Err(HealthError::HighSubsystemLatency {
+                    subsystem: subsystem.clone(),
+                    latency,
+                    max_latency,
+                });
+            }
+        }
+    }
     Ok(())
 }
 
@@ -63,13 +354,27 @@ fn is_healthy(v: serde_json::Value, config: &Config) -> Result<(), HealthError>
 pub async fn status(
     app_state: web::Data<AppState>,
 ) -> Result<impl Responder, crate::api::ServiceError> {
-    internal_status(&app_state).await.map(|res| web::Json(res))
+    Ok(web::Json(internal_status(&app_state)))
+}
+
+#[get("/status/history")]
+pub async fn status_history(
+    app_state: web::Data<AppState>,
+) -> Result<impl Responder, api::ServiceError> {
+    Ok(web::Json(
+        app_state.status_history.lock().unwrap().to_json(),
+    ))
 }
 
 #[get("/health")]
-pub async fn health(app_state: web::Data<AppState>) -> Result<impl Responder, api::ServiceError> {
-    let res = internal_status(&app_state).await?;
+pub async fn health(
+    query: web::Query<HealthQuery>,
+    app_state: web::Data<AppState>,
+) -> Result<impl Responder, api::ServiceError> {
+    let thresholds = HealthThresholds::resolve(&query, &app_state.config)
+        .ok_or(api::ServiceError::ArgumentError)?;
+    let res = internal_status(&app_state);
     Ok(web::Json(
-        json!({"status": is_healthy(res, &app_state.config).map(|_| "ok".to_string()).unwrap_or_else(|e| format!("{:?}", e))}),
+        json!({"status": is_healthy(res, &app_state.config, &thresholds).map(|_| "ok".to_string()).unwrap_or_else(|e| format!("{:?}", e))}),
     ))
 }
@@ -0,0 +1,175 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// The recent window is kept at the resolution of the status poll interval, at least one second.
+const RECENT_WINDOW_SEC: u64 = 60 * 60;
+/// The daily window is downsampled to one sample per minute.
+const DAILY_WINDOW_SEC: u64 = 24 * 60 * 60;
+const DAILY_RESOLUTION_SEC: u64 = 60;
+
+#[derive(Clone, Copy, Serialize)]
+pub struct StatusSample {
+    /// The unix timestamp of the sample in seconds. For downsampled samples, the start of the
+    /// interval.
+    pub timestamp: u64,
+    pub sync_block_height: Option<u64>,
+    pub sync_latency_sec: Option<f64>,
+    pub balance_block_diff: Option<u64>,
+}
+
+impl StatusSample {
+    /// Merges another sample of the same interval, keeping the latest block height and the worst
+    /// latency and block difference.
+    fn merge(&mut self, other: &StatusSample) {
+        self.sync_block_height = self.sync_block_height.max(other.sync_block_height);
+        self.sync_latency_sec = match (self.sync_latency_sec, other.sync_latency_sec) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.balance_block_diff = self.balance_block_diff.max(other.balance_block_diff);
+    }
+}
+
+/// A rolling window of samples downsampled to one sample per `resolution_sec` interval.
+struct Window {
+    resolution_sec: u64,
+    window_sec: u64,
+    samples: VecDeque<StatusSample>,
+}
+
+impl Window {
+    fn new(resolution_sec: u64, window_sec: u64) -> Self {
+        Self {
+            resolution_sec,
+            window_sec,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Merges the sample into the sample of its interval and evicts the samples that are older
+    /// than the window.
+    fn record(&mut self, sample: StatusSample) {
+        let interval_start = sample.timestamp - sample.timestamp % self.resolution_sec;
+        match self.samples.back_mut() {
+            Some(last) if last.timestamp == interval_start => last.merge(&sample),
+            _ => self.samples.push_back(StatusSample {
+                timestamp: interval_start,
+                ..sample
+            }),
+        }
+
+        while self
+            .samples
+            .front()
+            .is_some_and(|s| s.timestamp + self.window_sec <= sample.timestamp)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "resolution_sec": self.resolution_sec,
+            "samples": self.samples,
+        })
+    }
+}
+
+/// Rolling windows of the sync status samples recorded by the status poller.
+pub struct StatusHistory {
+    recent: Window,
+    daily: Window,
+}
+
+impl StatusHistory {
+    /// Creates the history for samples recorded every `poll_interval`.
+    pub fn new(poll_interval: Duration) -> Self {
+        let recent_resolution_sec = poll_interval.as_secs_f64().ceil().max(1.0) as u64;
+        Self {
+            recent: Window::new(recent_resolution_sec, RECENT_WINDOW_SEC),
+            daily: Window::new(DAILY_RESOLUTION_SEC, DAILY_WINDOW_SEC),
+        }
+    }
+
+    pub fn record(&mut self, sample: StatusSample) {
+        self.recent.record(sample);
+        self.daily.record(sample);
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "last_hour": self.recent.to_json(),
+            "last_day": self.daily.to_json(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(timestamp: u64, sync_latency_sec: f64) -> StatusSample {
+        StatusSample {
+            timestamp,
+            sync_block_height: Some(timestamp),
+            sync_latency_sec: Some(sync_latency_sec),
+            balance_block_diff: Some(0),
+        }
+    }
+
+    #[test]
+    fn test_record_merges_samples_of_an_interval() {
+        let mut window = Window::new(60, DAILY_WINDOW_SEC);
+        window.record(sample(120, 2.0));
+        window.record(sample(150, 5.0));
+        window.record(sample(179, 1.0));
+        window.record(sample(180, 3.0));
+
+        let samples = window.samples.iter().collect::<Vec<_>>();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].timestamp, 120);
+        assert_eq!(samples[0].sync_block_height, Some(179));
+        assert_eq!(samples[0].sync_latency_sec, Some(5.0));
+        assert_eq!(samples[1].timestamp, 180);
+        assert_eq!(samples[1].sync_latency_sec, Some(3.0));
+    }
+
+    #[test]
+    fn test_record_evicts_samples_older_than_window() {
+        let mut window = Window::new(1, 10);
+        for timestamp in 100..=110 {
+            window.record(sample(timestamp, 1.0));
+        }
+
+        let timestamps = window
+            .samples
+            .iter()
+            .map(|s| s.timestamp)
+            .collect::<Vec<_>>();
+        assert_eq!(timestamps, (101..=110).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_recent_resolution_follows_poll_interval() {
+        let history = StatusHistory::new(Duration::from_millis(500));
+        assert_eq!(history.to_json()["last_hour"]["resolution_sec"], 1);
+
+        let mut history = StatusHistory::new(Duration::from_millis(2500));
+        history.record(sample(100, 1.0));
+        history.record(sample(101, 4.0));
+        history.record(sample(103, 2.0));
+        let json = history.to_json();
+        assert_eq!(json["last_hour"]["resolution_sec"], 3);
+        assert_eq!(
+            json["last_hour"]["samples"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|s| (s["timestamp"].as_u64(), s["sync_latency_sec"].as_f64()))
+                .collect::<Vec<_>>(),
+            vec![(Some(99), Some(4.0)), (Some(102), Some(2.0))]
+        );
+        assert_eq!(json["last_day"]["resolution_sec"], DAILY_RESOLUTION_SEC);
+    }
+}